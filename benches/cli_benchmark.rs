@@ -4,33 +4,62 @@ use clap::Parser;
 use criterion::{BatchSize, Criterion, criterion_group, criterion_main};
 
 use sound_themer::{
+    cache::clear_sound_cache,
     cli::{Cli, evaluate_cli},
     config::{TOMLConfig, init_toml_config},
+    error::ThemerError,
     theme::reset_selected_theme,
 };
 
+/// # Documentation
+/// How the sound cache is prepared for each iteration
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum CacheSetup {
+    /// Use the cache as it was left by the previous iterations
+    Unchanged,
+    /// Fill the cache before the benchmark starts, so every sound is found in it
+    Hit,
+    /// Clear the cache before each iteration, so every sound is searched for and probed
+    Miss,
+}
+
 /// # Documentation
 /// Call `evaluate_cli()` with the given arguments parsed by `Cli::parse_from(args)`
-fn evaluate_cli_benchmark_inner<S: AsRef<str>>(c: &mut Criterion, args: &[S]) {
+fn evaluate_cli_benchmark_inner<S: AsRef<str>>(c: &mut Criterion, args: &[S], cache_setup: CacheSetup) {
     let args = iter::once("sound_themer")
         .chain(args.iter().map(AsRef::as_ref))
         .collect::<Vec<_>>();
 
+    let name_suffix = match cache_setup {
+        CacheSetup::Unchanged => "",
+        CacheSetup::Hit => {
+            // Run the arguments once, so the sounds they use are cached
+            let _ = evaluate_cli(&Cli::parse_from(args.iter()), std::io::sink());
+
+            " (cache hit)"
+        }
+        CacheSetup::Miss => " (cache miss)",
+    };
+
     c.bench_function(
         // Generate the name for this using the arguments
-        format!("Evaluate CLI: \"{}\"", args.join(" ")).as_str(),
+        format!("Evaluate CLI{name_suffix}: \"{}\"", args.join(" ")).as_str(),
         |b| {
             b.iter_batched(
                 || {
                     {
                         // Include config initialisation in the benchmark
-                        let config: LazyLock<TOMLConfig> = LazyLock::new(init_toml_config);
+                        let config: LazyLock<Result<TOMLConfig, ThemerError>> = LazyLock::new(init_toml_config);
                         #[allow(clippy::explicit_auto_deref)]
-                        let _cfg_ref: &TOMLConfig = &*config;
+                        let _cfg_ref: &Result<TOMLConfig, ThemerError> = &*config;
+                    }
+
+                    if cache_setup == CacheSetup::Miss {
+                        clear_sound_cache().expect("The sound cache couldn't be cleared");
                     }
 
                     // Reset the selected theme to config default
-                    reset_selected_theme();
+                    reset_selected_theme().expect("The selected theme couldn't be reset");
                 },
                 |_| {
                     // Add the package name before the arguments, and parse it as a Cli object
@@ -46,27 +75,49 @@ fn evaluate_cli_benchmark_inner<S: AsRef<str>>(c: &mut Criterion, args: &[S]) {
 }
 
 /// # Documentation
-/// Call `evaluate_cli()` with "play --duration 0 complete" as the args
+/// Call `evaluate_cli()` with "play --no-daemon --duration 0 complete" as the args
 fn evaluate_cli_play_duration_zero_benchmark(c: &mut Criterion) {
-    evaluate_cli_benchmark_inner(c, &["play", "--duration", "0", "complete"])
+    evaluate_cli_benchmark_inner(
+        c,
+        &["play", "--no-daemon", "--duration", "0", "complete"],
+        CacheSetup::Unchanged,
+    )
 }
 
 /// # Documentation
-/// Call `evaluate_cli()` with "--theme freedesktop play --duration 0 complete" as the args
+/// Call `evaluate_cli()` with "--theme freedesktop play --no-daemon --duration 0 complete" as the args
 fn evaluate_cli_play_duration_zero_and_theme_benchmark(c: &mut Criterion) {
-    evaluate_cli_benchmark_inner(c, &["--theme", "freedesktop", "play", "--duration", "0", "complete"])
+    evaluate_cli_benchmark_inner(
+        c,
+        &["--theme", "freedesktop", "play", "--no-daemon", "--duration", "0", "complete"],
+        CacheSetup::Unchanged,
+    )
 }
 
 /// # Documentation
 /// Call `evaluate_cli()` with "list" as the args
 fn evaluate_cli_list_benchmark(c: &mut Criterion) {
-    evaluate_cli_benchmark_inner(c, &["list"])
+    evaluate_cli_benchmark_inner(c, &["list"], CacheSetup::Unchanged)
+}
+
+/// # Documentation
+/// Call `evaluate_cli()` with "play --no-daemon --duration 0 complete" as the args, where the sound is already cached
+fn evaluate_cli_play_cache_hit_benchmark(c: &mut Criterion) {
+    evaluate_cli_benchmark_inner(c, &["play", "--no-daemon", "--duration", "0", "complete"], CacheSetup::Hit)
+}
+
+/// # Documentation
+/// Call `evaluate_cli()` with "play --no-daemon --duration 0 complete" as the args, where the cache is cleared before each play
+fn evaluate_cli_play_cache_miss_benchmark(c: &mut Criterion) {
+    evaluate_cli_benchmark_inner(c, &["play", "--no-daemon", "--duration", "0", "complete"], CacheSetup::Miss)
 }
 
 criterion_group!(
     benches,
     evaluate_cli_play_duration_zero_benchmark,
     evaluate_cli_play_duration_zero_and_theme_benchmark,
-    evaluate_cli_list_benchmark
+    evaluate_cli_list_benchmark,
+    evaluate_cli_play_cache_hit_benchmark,
+    evaluate_cli_play_cache_miss_benchmark
 );
 criterion_main!(benches);
@@ -0,0 +1,60 @@
+use std::{
+    fs::{self, File, OpenOptions},
+    path::Path,
+};
+
+use crate::error::ThemerError;
+
+/// # Documentation
+/// Take an exclusive advisory lock for the file at `path`, which is held until the returned `File` is dropped.
+/// The lock is taken on a separate `.lock` file, since `write_file_atomically()` replaces the file itself
+///
+/// # Errors
+/// Returns an error if the directory of the file could not be created
+/// Returns an error if the lock file could not be opened or locked
+pub fn lock_file<P: AsRef<Path>>(path: P) -> Result<File, ThemerError> {
+    let path = path.as_ref();
+
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(|e| ThemerError::PathCreateError(e.to_string()))?;
+    }
+
+    let lock_path = format!("{}.lock", path.display());
+    let lock_file = OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(&lock_path)
+        .map_err(|e| ThemerError::FileReadWriteError(format!("'{lock_path}': {e}")))?;
+
+    // Blocks until any other process or thread has finished with the file
+    lock_file
+        .lock()
+        .map_err(|e| ThemerError::FileReadWriteError(format!("'{lock_path}': {e}")))?;
+
+    Ok(lock_file)
+}
+
+/// # Documentation
+/// Write `contents` to a temporary file next to `path`, then rename it over `path`, so the file is never seen partly
+/// written. The lock from `lock_file()` should be held, so only one temporary file is written at once
+///
+/// # Errors
+/// Returns an error if the directory of the file could not be created
+/// Returns an error if the temporary file could not be written, or could not be renamed to `path`
+pub fn write_file_atomically<P: AsRef<Path>, C: AsRef<[u8]>>(path: P, contents: C) -> Result<(), ThemerError> {
+    let path = path.as_ref();
+
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(|e| ThemerError::PathCreateError(e.to_string()))?;
+    }
+
+    let temp_path = format!("{}.tmp", path.display());
+    fs::write(&temp_path, contents).map_err(|e| ThemerError::FileReadWriteError(format!("'{temp_path}': {e}")))?;
+
+    fs::rename(&temp_path, path).map_err(|e| {
+        let _ = fs::remove_file(&temp_path);
+
+        ThemerError::FileReadWriteError(format!("'{}': {e}", path.display()))
+    })
+}
@@ -0,0 +1,352 @@
+use std::{
+    collections::{BTreeMap, btree_map::Entry},
+    fs,
+    ops::{Deref, DerefMut},
+    path::Path,
+    sync::{Mutex, MutexGuard},
+    time::{Duration, UNIX_EPOCH},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    atomic_file::{lock_file, write_file_atomically},
+    error::ThemerError,
+    xdg::get_cache_home,
+};
+
+const CACHE_DIR_SHORT: &str = "sound_themer";
+const SOUND_CACHE_FILE_NAME: &str = "sounds.toml";
+
+static SOUND_CACHE: Mutex<LoadedSoundCache> = Mutex::new(LoadedSoundCache {
+    cache: SoundCache { themes: Vec::new() },
+    loaded: false,
+});
+
+// The sound cache, along with whether it has been read from the cache file yet
+struct LoadedSoundCache {
+    cache: SoundCache,
+    loaded: bool,
+}
+
+/// # Documentation
+/// The sound files found for each theme, so they don't need to be searched for or probed again until their directories change
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct SoundCache {
+    #[serde(default)]
+    pub themes: Vec<CachedTheme>,
+}
+
+/// # Documentation
+/// The sounds found for a theme in a locale, which are only used while none of `directories` have changed
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct CachedTheme {
+    pub theme: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub locale: Option<String>,
+    /// # Documentation
+    /// Every directory which is searched for the theme's sounds, in the order they are searched
+    #[serde(default)]
+    pub directories: Vec<CachedDirectory>,
+    #[serde(default)]
+    pub sounds: BTreeMap<String, CachedSound>,
+}
+
+/// # Documentation
+/// A directory which is searched for sounds, along with the extensions which are searched for
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct CachedDirectory {
+    pub path: String,
+    pub sound_exts: Vec<String>,
+    /// # Documentation
+    /// When the directory was last changed in nanoseconds since the Unix epoch, unset if it doesn't exist
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub modified: Option<u64>,
+}
+
+/// # Documentation
+/// The sound file found for a sound name (Unset if none was found), along with its details once it has been probed
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct CachedSound {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub info: Option<SoundInfo>,
+}
+
+/// # Documentation
+/// The details of a sound file which are read by probing it, which are only used while it hasn't been changed
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq)]
+pub struct SoundInfo {
+    /// # Documentation
+    /// When the file was last changed in nanoseconds since the Unix epoch
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub modified: Option<u64>,
+    /// # Documentation
+    /// The length of the sound in seconds
+    pub duration: f64,
+    pub sample_rate: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub channels: Option<u16>,
+}
+
+impl SoundInfo {
+    #[must_use]
+    pub fn duration(&self) -> Duration {
+        Duration::from_secs_f64(self.duration)
+    }
+}
+
+impl CachedDirectory {
+    /// # Documentation
+    /// Read when the directory at `path` was last changed
+    #[must_use]
+    pub fn new<S: AsRef<str>>(path: S, sound_exts: Vec<String>) -> Self {
+        Self {
+            path: path.as_ref().to_string(),
+            sound_exts,
+            modified: get_modified_time(path),
+        }
+    }
+
+    /// # Documentation
+    /// Whether the directory has been changed, created, or removed since it was cached
+    #[must_use]
+    pub fn has_changed(&self) -> bool {
+        get_modified_time(&self.path) != self.modified
+    }
+}
+
+impl CachedTheme {
+    /// # Documentation
+    /// Count the cached sounds which have a file, and which of those have been probed
+    #[must_use]
+    pub fn count_sounds(&self) -> (usize, usize) {
+        let found = self.sounds.values().filter(|sound| sound.path.is_some()).count();
+        let probed = self.sounds.values().filter(|sound| sound.info.is_some()).count();
+
+        (found, probed)
+    }
+}
+
+impl SoundCache {
+    /// # Documentation
+    /// Get the cached sounds for `theme` in `locale`, which are cleared if `directories` aren't the same as when they were
+    /// cached (e.g: a directory was changed, or the theme now searches different directories)
+    pub fn get_theme_mut<S: AsRef<str>>(
+        &mut self,
+        theme: S,
+        locale: Option<&str>,
+        directories: &[CachedDirectory],
+    ) -> &mut CachedTheme {
+        let index = if let Some(index) = self
+            .themes
+            .iter()
+            .position(|cached_theme| cached_theme.theme == theme.as_ref() && cached_theme.locale.as_deref() == locale)
+        {
+            index
+        } else {
+            self.themes.push(CachedTheme {
+                theme: theme.as_ref().to_string(),
+                locale: locale.map(ToString::to_string),
+                directories: directories.to_vec(),
+                sounds: BTreeMap::new(),
+            });
+
+            self.themes.len() - 1
+        };
+
+        let cached_theme = &mut self.themes[index];
+        if cached_theme.directories != directories {
+            cached_theme.directories = directories.to_vec();
+            cached_theme.sounds.clear();
+        }
+
+        cached_theme
+    }
+
+    /// # Documentation
+    /// Whether the sounds for `theme` in `locale` are cached, and were cached with the same `directories`
+    #[must_use]
+    pub fn has_theme<S: AsRef<str>>(&self, theme: S, locale: Option<&str>, directories: &[CachedDirectory]) -> bool {
+        self.themes.iter().any(|cached_theme| {
+            cached_theme.theme == theme.as_ref()
+                && cached_theme.locale.as_deref() == locale
+                && cached_theme.directories == directories
+        })
+    }
+
+    /// # Documentation
+    /// Add the themes and sounds from `other` (e.g: the cache file written by another process) which aren't in this
+    /// cache. Where both caches have a theme or sound, the one in this cache is kept, unless only `other` has probed it
+    pub fn merge(&mut self, other: Self) {
+        for other_theme in other.themes {
+            let Some(cached_theme) = self
+                .themes
+                .iter_mut()
+                .find(|cached_theme| cached_theme.theme == other_theme.theme && cached_theme.locale == other_theme.locale)
+            else {
+                self.themes.push(other_theme);
+                continue;
+            };
+
+            // The sounds in one of the caches are out of date, and are replaced when the theme is next used
+            if cached_theme.directories != other_theme.directories {
+                continue;
+            }
+
+            for (name, other_sound) in other_theme.sounds {
+                match cached_theme.sounds.entry(name) {
+                    Entry::Vacant(entry) => {
+                        entry.insert(other_sound);
+                    }
+                    Entry::Occupied(mut entry) => {
+                        let sound = entry.get_mut();
+                        if sound.path == other_sound.path && sound.info.is_none() {
+                            sound.info = other_sound.info;
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// # Documentation
+    /// Get the details of the sound file at `path`, if they were probed when it was last changed at `modified`
+    #[must_use]
+    pub fn get_sound_info<S: AsRef<str>>(&self, path: S, modified: Option<u64>) -> Option<SoundInfo> {
+        self.themes
+            .iter()
+            .flat_map(|cached_theme| cached_theme.sounds.values())
+            .filter(|sound| sound.path.as_deref() == Some(path.as_ref()))
+            .find_map(|sound| sound.info.filter(|info| info.modified == modified))
+    }
+
+    /// # Documentation
+    /// Set the details of every cached sound whose file is at `path`, returning whether any sounds were changed
+    pub fn set_sound_info<S: AsRef<str>>(&mut self, path: S, info: SoundInfo) -> bool {
+        let mut changed = false;
+
+        for sound in self
+            .themes
+            .iter_mut()
+            .flat_map(|cached_theme| cached_theme.sounds.values_mut())
+        {
+            if sound.path.as_deref() == Some(path.as_ref()) && sound.info != Some(info) {
+                sound.info = Some(info);
+                changed = true;
+            }
+        }
+
+        changed
+    }
+}
+
+/// # Documentation
+/// Get when the file or directory at `path` was last changed in nanoseconds since the Unix epoch, unset if it doesn't exist
+#[must_use]
+pub fn get_modified_time<S: AsRef<str>>(path: S) -> Option<u64> {
+    let modified = fs::metadata(path.as_ref()).and_then(|metadata| metadata.modified()).ok()?;
+
+    u64::try_from(modified.duration_since(UNIX_EPOCH).ok()?.as_nanos()).ok()
+}
+
+/// # Documentation
+/// Get the path of the file which caches the sounds found for each theme
+///
+/// # Errors
+/// Returns an error if neither `$XDG_CACHE_HOME` nor `$HOME` are set
+pub fn get_sound_cache_path() -> Result<String, ThemerError> {
+    let cache_home = get_cache_home().ok_or_else(|| ThemerError::EnvironmentVarError(String::from("$XDG_CACHE_HOME")))?;
+
+    Ok(format!("{cache_home}/{CACHE_DIR_SHORT}/{SOUND_CACHE_FILE_NAME}"))
+}
+
+/// # Documentation
+/// The locked sound cache, which can be read and changed without copying it, then saved to the cache file using `save()`.
+/// Other threads which use the cache wait until it is dropped
+pub struct SoundCacheGuard {
+    guard: MutexGuard<'static, LoadedSoundCache>,
+}
+
+impl Deref for SoundCacheGuard {
+    type Target = SoundCache;
+
+    fn deref(&self) -> &Self::Target {
+        &self.guard.cache
+    }
+}
+
+impl DerefMut for SoundCacheGuard {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.guard.cache
+    }
+}
+
+impl SoundCacheGuard {
+    /// # Documentation
+    /// Merge the cache file into the sound cache, so the sounds cached by other processes aren't lost, then replace the
+    /// cache file with the merged cache. The cache file is locked while it is read and written
+    ///
+    /// # Errors
+    /// Returns an error if `get_sound_cache_path()` fails
+    /// Returns an error if `lock_file()` fails
+    /// Returns an error if `write_file_atomically()` fails
+    pub fn save(&mut self) -> Result<(), ThemerError> {
+        let cache_path = get_sound_cache_path()?;
+        let _lock = lock_file(&cache_path)?;
+
+        self.guard.cache.merge(read_sound_cache_file(&cache_path));
+
+        let cache_str = toml::to_string(&self.guard.cache)?;
+        write_file_atomically(&cache_path, cache_str)
+    }
+}
+
+// A missing or unreadable cache file is rebuilt as sounds are found
+fn read_sound_cache_file<S: AsRef<str>>(cache_path: S) -> SoundCache {
+    fs::read_to_string(cache_path.as_ref())
+        .ok()
+        .and_then(|cache| toml::from_str::<SoundCache>(&cache).ok())
+        .unwrap_or_default()
+}
+
+/// # Documentation
+/// Lock the sound cache, which is read from the cache file the first time it is used
+///
+/// # Errors
+/// Returns an error if `SOUND_CACHE` could not be locked
+/// Returns an error if `get_sound_cache_path()` fails
+pub fn lock_sound_cache() -> Result<SoundCacheGuard, ThemerError> {
+    let mut guard = SOUND_CACHE.lock().map_err(|e| ThemerError::MutexLockError(e.to_string()))?;
+
+    if !guard.loaded {
+        guard.cache = read_sound_cache_file(get_sound_cache_path()?);
+        guard.loaded = true;
+    }
+
+    Ok(SoundCacheGuard { guard })
+}
+
+/// # Documentation
+/// Empty the sound cache, then remove the cache file, returning whether there was a file to remove
+///
+/// # Errors
+/// Returns an error if `lock_sound_cache()` fails
+/// Returns an error if `get_sound_cache_path()` or `lock_file()` fails
+/// Returns an error if the cache file exists, but could not be removed
+pub fn clear_sound_cache() -> Result<bool, ThemerError> {
+    let mut cache = lock_sound_cache()?;
+    *cache = SoundCache::default();
+
+    let cache_path_str = get_sound_cache_path()?;
+    if !Path::new(&cache_path_str).exists() {
+        return Ok(false);
+    }
+
+    let _lock = lock_file(&cache_path_str)?;
+    fs::remove_file(&cache_path_str).map_err(|e| ThemerError::FileReadWriteError(e.to_string()))?;
+    drop(cache);
+
+    Ok(true)
+}
@@ -0,0 +1,259 @@
+use std::{collections::HashMap, iter, path::Path, str::FromStr};
+
+use crate::{
+    config::get_toml_config,
+    error::ThemerError,
+    sound::{PlaybackOptions, PlaybackOverrides, PlaybackStep, ResolvedSound, resolve_sound_steps},
+    volume::Volume,
+};
+
+/// # Documentation
+/// The name of the sound to play, which is resolved through the mapping and theme in the same way as `play`
+pub const PROP_EVENT_ID: &str = "event.id";
+/// # Documentation
+/// A human readable description of the event, which is shown instead of the sound name
+pub const PROP_EVENT_DESCRIPTION: &str = "event.description";
+/// # Documentation
+/// A sound file to play instead of looking up `event.id`
+pub const PROP_MEDIA_FILENAME: &str = "media.filename";
+/// # Documentation
+/// The role of the sound (e.g: `event`), which is accepted for compatibility, but doesn't change how it is played
+pub const PROP_MEDIA_ROLE: &str = "media.role";
+/// # Documentation
+/// The volume of the sound in decibels (e.g: `-6.0`), multiplied by the config volumes
+pub const PROP_CANBERRA_VOLUME: &str = "canberra.volume";
+
+/// # Documentation
+/// A sound requested in the same way as libcanberra, using its event and media properties
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CanberraEvent {
+    pub event_id: Option<String>,
+    pub description: Option<String>,
+    pub file: Option<String>,
+    pub media_role: Option<String>,
+    pub volume: Option<Volume>,
+}
+
+impl CanberraEvent {
+    /// # Documentation
+    /// Read the event from libcanberra properties, where any properties which aren't used are ignored
+    ///
+    /// # Errors
+    /// Returns an error if `canberra.volume` isn't a decibel value
+    /// Returns an error if neither `event.id` nor `media.filename` are set
+    pub fn from_properties(properties: &HashMap<String, String>) -> Result<Self, ThemerError> {
+        let get_property = |key: &str| properties.get(key).filter(|value| !value.trim().is_empty()).cloned();
+
+        let volume = get_property(PROP_CANBERRA_VOLUME)
+            .map(|decibels| {
+                // libcanberra volumes are always in decibels, with or without the unit
+                let decibels = decibels.trim();
+                let decibels = decibels
+                    .strip_suffix("dB")
+                    .or_else(|| decibels.strip_suffix("db"))
+                    .unwrap_or(decibels);
+
+                Volume::from_str(&format!("{decibels}dB")).map_err(|e| ThemerError::CanberraPropertyError {
+                    key: PROP_CANBERRA_VOLUME.to_string(),
+                    e: e.to_string(),
+                })
+            })
+            .transpose()?;
+
+        let event = Self {
+            event_id: get_property(PROP_EVENT_ID),
+            description: get_property(PROP_EVENT_DESCRIPTION),
+            file: get_property(PROP_MEDIA_FILENAME),
+            media_role: get_property(PROP_MEDIA_ROLE),
+            volume,
+        };
+
+        if event.event_id.is_none() && event.file.is_none() {
+            return Err(ThemerError::CanberraPropertyError {
+                key: PROP_EVENT_ID.to_string(),
+                e: format!("Either '{PROP_EVENT_ID}' or '{PROP_MEDIA_FILENAME}' must be set"),
+            });
+        }
+
+        Ok(event)
+    }
+
+    /// # Documentation
+    /// Get the name which describes the event, which is its description if it has one
+    #[must_use]
+    pub fn name(&self) -> String {
+        self.description
+            .clone()
+            .or_else(|| self.event_id.clone())
+            .or_else(|| self.file.clone())
+            .unwrap_or_default()
+    }
+
+    /// # Documentation
+    /// Find the sound files for the event, where `media.filename` is played as it is, otherwise `event.id` is resolved
+    /// through the mapping and theme by `resolve_sound_steps()`
+    ///
+    /// # Errors
+    /// Returns an error if `media.filename` doesn't exist
+    /// Returns an error if `get_toml_config()` or `resolve_sound_steps()` fails
+    pub fn resolve(&self) -> Result<Vec<PlaybackStep>, ThemerError> {
+        match (&self.file, &self.event_id) {
+            (Some(file), _) => {
+                if !Path::new(file).is_file() {
+                    return Err(ThemerError::SoundPathsNotFoundError(format!("'{file}'")));
+                }
+
+                // The file isn't in a theme, so only the global volume is multiplied in
+                let volume = Volume::product(iter::once(get_toml_config()?.volume).chain(self.volume));
+
+                Ok(vec![PlaybackStep::Sound(ResolvedSound {
+                    path: file.clone(),
+                    options: PlaybackOptions {
+                        volume: volume.value(),
+                        ..PlaybackOptions::default()
+                    },
+                })])
+            }
+            (None, Some(event_id)) => resolve_sound_steps(
+                event_id,
+                &PlaybackOverrides {
+                    volume: self.volume,
+                    ..PlaybackOverrides::default()
+                },
+            ),
+            (None, None) => Err(ThemerError::CanberraPropertyError {
+                key: PROP_EVENT_ID.to_string(),
+                e: format!("Either '{PROP_EVENT_ID}' or '{PROP_MEDIA_FILENAME}' must be set"),
+            }),
+        }
+    }
+}
+
+/// # Documentation
+/// Read a property written as `key=value` (e.g: from `--property media.role=event`)
+///
+/// # Errors
+/// Returns an error if the property has no `=`, or no key
+pub fn parse_property(s: &str) -> Result<(String, String), ThemerError> {
+    match s.split_once('=') {
+        Some((key, value)) if !key.trim().is_empty() => Ok((key.trim().to_string(), value.to_string())),
+        _ => Err(ThemerError::CanberraPropertyError {
+            key: s.to_string(),
+            e: String::from("Properties must be written as `key=value` (e.g: media.role=event)"),
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn to_properties(properties: &[(&str, &str)]) -> HashMap<String, String> {
+        properties
+            .iter()
+            .map(|(key, value)| ((*key).to_string(), (*value).to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn volume_is_read_as_decibels() -> Result<(), ThemerError> {
+        for decibels in ["-6", "-6dB", "-6db", " -6 dB "] {
+            let event =
+                CanberraEvent::from_properties(&to_properties(&[(PROP_EVENT_ID, "bell"), (PROP_CANBERRA_VOLUME, decibels)]))?;
+
+            assert_eq!(
+                event.volume,
+                Some(Volume::from_str("-6dB")?),
+                "canberra.volume = '{decibels}'"
+            );
+        }
+
+        let event = CanberraEvent::from_properties(&to_properties(&[(PROP_EVENT_ID, "bell"), (PROP_CANBERRA_VOLUME, "0")]))?;
+        assert_eq!(event.volume, Some(Volume::default()));
+
+        Ok(())
+    }
+
+    #[test]
+    fn invalid_volume_is_an_error() {
+        for decibels in ["loud", "50%", "100dB"] {
+            let result =
+                CanberraEvent::from_properties(&to_properties(&[(PROP_EVENT_ID, "bell"), (PROP_CANBERRA_VOLUME, decibels)]));
+
+            assert!(
+                matches!(result, Err(ThemerError::CanberraPropertyError { ref key, .. }) if key == PROP_CANBERRA_VOLUME),
+                "canberra.volume = '{decibels}' gave {result:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn event_id_or_filename_is_required() -> Result<(), ThemerError> {
+        for properties in [
+            to_properties(&[]),
+            to_properties(&[(PROP_MEDIA_ROLE, "event"), (PROP_EVENT_DESCRIPTION, "Bell")]),
+        ] {
+            let result = CanberraEvent::from_properties(&properties);
+
+            assert!(
+                matches!(result, Err(ThemerError::CanberraPropertyError { ref key, .. }) if key == PROP_EVENT_ID),
+                "{properties:?} gave {result:?}"
+            );
+        }
+
+        let event = CanberraEvent::from_properties(&to_properties(&[(PROP_MEDIA_FILENAME, "/tmp/bell.oga")]))?;
+        assert_eq!(event.file.as_deref(), Some("/tmp/bell.oga"));
+        assert_eq!(event.event_id, None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn empty_values_are_ignored() -> Result<(), ThemerError> {
+        let event = CanberraEvent::from_properties(&to_properties(&[
+            (PROP_EVENT_ID, "bell"),
+            (PROP_EVENT_DESCRIPTION, ""),
+            (PROP_MEDIA_FILENAME, "  "),
+            (PROP_MEDIA_ROLE, "\t"),
+            (PROP_CANBERRA_VOLUME, " "),
+        ]))?;
+
+        assert_eq!(
+            event,
+            CanberraEvent {
+                event_id: Some(String::from("bell")),
+                ..CanberraEvent::default()
+            }
+        );
+
+        // Empty values don't count as being set
+        let result = CanberraEvent::from_properties(&to_properties(&[(PROP_EVENT_ID, " "), (PROP_MEDIA_FILENAME, "")]));
+        assert!(matches!(result, Err(ThemerError::CanberraPropertyError { .. })));
+
+        Ok(())
+    }
+
+    #[test]
+    fn name_prefers_the_description() -> Result<(), ThemerError> {
+        let event = CanberraEvent::from_properties(&to_properties(&[(PROP_EVENT_ID, "bell"), (PROP_EVENT_DESCRIPTION, "Bell")]))?;
+        assert_eq!(event.name(), "Bell");
+
+        let event = CanberraEvent::from_properties(&to_properties(&[(PROP_EVENT_ID, "bell")]))?;
+        assert_eq!(event.name(), "bell");
+
+        Ok(())
+    }
+
+    #[test]
+    fn properties_are_split_at_the_first_equals() -> Result<(), ThemerError> {
+        assert_eq!(
+            parse_property(" media.role =a=b")?,
+            (String::from("media.role"), String::from("a=b"))
+        );
+        assert_eq!(parse_property("event.id=")?, (String::from("event.id"), String::new()));
+        assert!(parse_property("event.id").is_err());
+        assert!(parse_property("=bell").is_err());
+
+        Ok(())
+    }
+}
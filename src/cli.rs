@@ -1,12 +1,35 @@
-use std::{ffi::OsStr, fs, io::Write, path::Path};
+use std::{
+    collections::{HashMap, HashSet},
+    ffi::OsStr,
+    fs,
+    io::Write,
+    path::Path,
+    process::Command,
+};
 
-use clap::{Parser, Subcommand};
+use clap::{Args, Parser, Subcommand};
 
+#[cfg(feature = "dbus")]
+use crate::dbus::run_dbus_service;
 use crate::{
-    duration::PlaybackDuration,
+    cache::{clear_sound_cache, get_sound_cache_path, lock_sound_cache},
+    canberra::{CanberraEvent, PROP_CANBERRA_VOLUME, PROP_EVENT_DESCRIPTION, PROP_EVENT_ID, PROP_MEDIA_FILENAME, parse_property},
+    config::{
+        BUILTIN_CONFIG_NAME, get_config_file_layers, get_config_path, get_config_search_paths, get_layered_toml_config,
+        get_resolved_toml_config, get_selected_config_path, get_toml_config, init_config_file, select_config_overrides,
+        select_config_path, validate_config,
+    },
+    daemon::{DaemonRequest, get_socket_path, run_daemon, send_daemon_request},
+    duration::{PlaybackDuration, PlaybackOffset, PlaybackRange},
     error::ThemerError,
-    sound::play_sound,
-    theme::{get_selected_theme, get_selected_theme_paths, select_random_theme, select_theme_by_name},
+    layer::{ConfigOrigin, ConfigOverride, flatten_toml_table, get_env_config_overrides},
+    locale::select_locale,
+    sound::{
+        PlaybackOverrides, PlaybackStep, ResolvedSound, SoundItem, cache_selected_theme_sounds, get_mapping_entry,
+        play_parallel_playback_steps, play_playback_steps, resolve_sound_steps,
+    },
+    theme::{get_selected_theme, get_selected_theme_paths, select_random_theme, select_theme, select_theme_by_name},
+    volume::Volume,
 };
 
 #[derive(Parser, Debug)]
@@ -22,33 +45,214 @@ pub struct Cli {
     #[arg(short, long, conflicts_with("theme"))]
     pub random: bool,
 
+    /// Override the locale used to find localised sounds, instead of reading it from the environment
+    #[arg(long)]
+    pub locale: Option<String>,
+
+    /// Read the config from this file, instead of searching for it (Overrides `$SOUND_THEMER_CONFIG`)
+    #[arg(long)]
+    pub config: Option<String>,
+
+    /// Override a value in the config using its dotted path, can be set multiple times (e.g: volume=0.5, player.backend=paplay)
+    #[arg(long = "set", value_name = "KEY=VALUE", value_parser)]
+    pub set: Vec<ConfigOverride>,
+
+    /// Print how each sound name is mapped to a sound file before it is played
+    #[arg(long)]
+    pub verbose: bool,
+
     #[command(subcommand)]
     pub commands: CliCommands,
 }
 
 #[derive(Subcommand, Debug)]
 pub enum CliCommands {
-    /// A command to play a sound given a `sound_name`
-    #[command(alias = "p", about = "Play a sound from the sound theme using a given sound name.")]
+    /// A command to play sounds given their `sound_names`
+    #[command(
+        alias = "p",
+        about = "Play sounds from the sound theme using their names, one after another or all at once."
+    )]
     Play {
-        /// The name of the sound which will be played (from the selected theme)
-        sound_name: String,
+        /// The names of the sounds which will be played (from the selected theme), which can each set their own duration
+        /// (e.g: complete, message@250ms, bell@50%)
+        #[arg(required = true, num_args = 1..)]
+        sound_names: Vec<SoundItem>,
 
-        /// Limit the playback length of the sound (e.g: 250ms, 1s, 50%, 2)
+        /// Limit the playback length of each sound which doesn't set its own, from where it starts (e.g: 250ms, 1s, 50%, 2)
         #[arg(short, long, value_parser)]
         duration: Option<PlaybackDuration>,
+
+        /// Start playing the sound from this position, measured from the end if negative (e.g: 250ms, 10%, -1s)
+        #[arg(short, long, value_parser, allow_hyphen_values(true), conflicts_with("range"))]
+        start: Option<PlaybackOffset>,
+
+        /// Stop playing the sound at this position, measured from the end if negative (e.g: 1s, 90%, -200ms)
+        #[arg(short, long, value_parser, allow_hyphen_values(true), conflicts_with_all(["range", "duration"]))]
+        end: Option<PlaybackOffset>,
+
+        /// Play a section of the sound, where either side can be left empty (e.g: 250ms..1s, 20%..80%, ..-200ms)
+        #[arg(long, value_parser, allow_hyphen_values(true), conflicts_with("duration"))]
+        range: Option<PlaybackRange>,
+
+        /// Set the volume of the sound, multiplied by the config volumes (e.g: 0.8, 80%, -3dB, up to 1.5 or 150%)
+        #[arg(short, long, value_parser, allow_hyphen_values(true))]
+        volume: Option<Volume>,
+
+        /// Fade out the end of the sound over this duration, instead of the config's fade-out (e.g: 30ms, 0.1s, 5%)
+        #[arg(short, long, value_parser)]
+        fade_out: Option<PlaybackDuration>,
+
+        /// Play every sound at once, instead of one after another
+        #[arg(short, long, conflicts_with("gap"))]
+        parallel: bool,
+
+        /// Wait this long between each sound (e.g: 100ms, 0.5s)
+        #[arg(short, long, value_parser)]
+        gap: Option<PlaybackDuration>,
+
+        /// Play the sounds in this process, even if the daemon is running
+        #[arg(long)]
+        no_daemon: bool,
     },
     /// A command to list all the files in the current theme's directory
     #[command(alias = "l", alias = "ls", about = "List the sounds in the sound theme.")]
     List,
+    /// A command to manage the config file
+    #[command(about = "Create, show, validate, or edit the config file.")]
+    Config {
+        #[command(subcommand)]
+        command: ConfigCommands,
+    },
+    /// A command to manage the cache of sound files found for each theme
+    #[command(about = "Rebuild, clear, or show the cache of sound files found for each theme.")]
+    Cache {
+        #[command(subcommand)]
+        command: CacheCommands,
+    },
+    /// A command to run the daemon, or to send it a request
+    #[command(about = "Run the daemon which plays sounds without reading the config each time, or send it a request.")]
+    Daemon {
+        #[command(subcommand)]
+        request: Option<DaemonRequest>,
+    },
+    /// A command to run the D-Bus service
+    #[cfg(feature = "dbus")]
+    #[command(about = "Play the events requested through the session D-Bus, using libcanberra properties.")]
+    Dbus,
+    /// A command which takes the same arguments as `canberra-gtk-play`, so it can be used in its place
+    #[command(
+        name = "canberra-gtk-play",
+        about = "Play an event sound, using the same arguments as canberra-gtk-play."
+    )]
+    CanberraGtkPlay(CanberraGtkPlayArgs),
 }
 
+/// # Documentation
+/// The arguments of `canberra-gtk-play`, which are all passed to the sound as libcanberra properties
+#[derive(Args, Debug)]
+pub struct CanberraGtkPlayArgs {
+    /// The event ID of the sound, which is mapped to a sound file in the same way as `play`
+    #[arg(short, long)]
+    pub id: Option<String>,
+
+    /// Play this sound file, instead of looking up an event ID
+    #[arg(short, long)]
+    pub file: Option<String>,
+
+    /// A description of the event
+    #[arg(short, long)]
+    pub description: Option<String>,
+
+    /// Only accepted for compatibility, since the cache is managed with `sound_themer cache`
+    #[arg(short, long)]
+    pub cache_control: Option<String>,
+
+    /// How many times to play the sound
+    #[arg(short, long = "loop", default_value_t = 1, value_parser = clap::value_parser!(u32).range(1..))]
+    pub loop_count: u32,
+
+    /// The volume of the sound in decibels, multiplied by the config volumes (e.g: -6.0)
+    #[arg(short = 'V', long, allow_hyphen_values(true))]
+    pub volume: Option<String>,
+
+    /// Set any other libcanberra property, can be set multiple times (e.g: media.role=event)
+    #[arg(long = "property", value_name = "KEY=VALUE", value_parser = parse_property)]
+    pub properties: Vec<(String, String)>,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum ConfigCommands {
+    /// A command to write the default config to the user's config file
+    #[command(about = "Write the default config to the user's config file.")]
+    Init {
+        /// Overwrite the config file if it already exists
+        #[arg(short, long)]
+        force: bool,
+    },
+    /// A command to print the paths of the config files which are used
+    #[command(about = "Print the paths of the config files which are layered, from the highest priority to the lowest.")]
+    Path,
+    /// A command to print the config which is used
+    #[command(about = "Print the config which is used, after layering each config file and override.")]
+    Show {
+        /// Print each theme as it is used, with every layer of its mapping and the values from its index.theme
+        #[arg(short, long, conflicts_with("origin"))]
+        resolved: bool,
+
+        /// Print each value on its own line, along with the layer which set it (e.g: a config file, or `--set`)
+        #[arg(short, long, conflicts_with("resolved"))]
+        origin: bool,
+    },
+    /// A command to check the config file for mistakes
+    #[command(about = "Check the config file for mistakes, printing the line and column of each one.")]
+    Validate,
+    /// A command to open the config file in an editor, then check it for mistakes
+    #[command(about = "Open the config file in $VISUAL or $EDITOR, then check it for mistakes once it is saved.")]
+    Edit,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum CacheCommands {
+    /// A command to clear the cache, then find and probe every sound in each theme
+    #[command(about = "Clear the cache, then find and probe every sound in each theme in the config.")]
+    Rebuild,
+    /// A command to remove the cache file
+    #[command(about = "Remove the cache file, so each sound is searched for again the next time it is played.")]
+    Clear,
+    /// A command to print how many sounds are cached for each theme
+    #[command(about = "Print how many sounds are cached for each theme, and whether its directories have changed since.")]
+    Stats,
+}
+
+/// # Documentation
+/// The editor used by `config edit` when neither `$VISUAL` nor `$EDITOR` are set
+pub const DEFAULT_EDITOR: &str = "vi";
+
 /// # Errors
+/// Returns an error if the config file could not be changed to `cli.config`
 /// Returns an error if `Theme` could not be changed to `cli.theme`
-/// Returns an error if sound could not be played using `play_sound()`
+/// Returns an error if the locale could not be changed to `cli.locale`
+/// Returns an error if the sounds could not be resolved or played using `play_sound_items()`
 /// Returns an error if `get_selected_theme_path()` fails
 /// Returns an error if `fs::read_dir()` could not be called on `theme_path`
+/// Returns an error if `evaluate_config_command()` or `evaluate_cache_command()` fails
 pub fn evaluate_cli<W: Write>(cli: &Cli, mut out: W) -> Result<(), ThemerError> {
+    // Override the config file before anything reads the config
+    if let Some(config_path) = &cli.config {
+        select_config_path(Some(config_path.clone()))?;
+    }
+
+    // The theme from --theme is layered over the other overrides, so it is shown by `config show`
+    let mut config_overrides = cli.set.clone();
+    if let Some(theme) = &cli.theme {
+        config_overrides.push(ConfigOverride::from_string(
+            "theme_name",
+            theme,
+            ConfigOrigin::Cli(String::from("--theme")),
+        ));
+    }
+    select_config_overrides(config_overrides)?;
+
     // Override config theme with random theme (Cant be set at the same time as --theme so this won't be overridden)
     if cli.random {
         select_random_theme()?;
@@ -62,47 +266,500 @@ pub fn evaluate_cli<W: Write>(cli: &Cli, mut out: W) -> Result<(), ThemerError>
         select_theme_by_name(theme)?;
     }
 
+    // Override the locale from the environment with the cli parsed locale
+    if let Some(locale) = &cli.locale {
+        select_locale(Some(locale.clone()))?;
+    }
+
     match &cli.commands {
-        CliCommands::Play { sound_name, duration } => play_sound(sound_name, duration.clone())?,
-        CliCommands::List => {
-            // Get the theme path where the sound files are
-            let theme_paths = get_selected_theme_paths()?;
-
-            let sound_ext = get_selected_theme()?.sound_ext;
-
-            // List files in each of the folders
-            for (i, theme_path_str) in theme_paths.iter().enumerate() {
-                writeln!(out, "Listing '.{sound_ext}' files in '{theme_path_str}':")?;
-
-                // Check this full path exists
-                let theme_path = Path::new(&theme_path_str);
-                // Get all the files in this folder and convert to their file names
-                fs::read_dir(theme_path)
-                    .map_err(|e| ThemerError::FileReadWriteError(e.to_string()))?
-                    .flatten()
-                    .filter_map(|entry| {
-                        let path = entry.path();
-
-                        // Check if it is a file with the correct extension
-                        if path.is_file()
-                            && let Some(ext) = path.extension()
-                            && ext == OsStr::new(&sound_ext)
-                        {
-                            // Get the file name without the extension, then convert to String
-                            path.file_stem().map(|file_name| file_name.display().to_string())
-                        } else {
-                            None
-                        }
-                    })
-                    .try_for_each(|file| writeln!(out, "\t{file}"))?;
-
-                // Add an extra newline between different path directories
-                if i < theme_paths.len() - 1 {
-                    writeln!(out)?;
+        CliCommands::Play {
+            sound_names,
+            duration,
+            start,
+            end,
+            range,
+            volume,
+            fade_out,
+            parallel,
+            gap,
+            no_daemon,
+        } => {
+            let range = range.clone().unwrap_or_else(|| PlaybackRange {
+                start: start.clone(),
+                end: end.clone(),
+            });
+
+            // The daemon's config is only used if this process would have read the same config
+            if !no_daemon && !overrides_config(cli)? {
+                let request = DaemonRequest::Play {
+                    sound_names: sound_names.clone(),
+                    duration: duration.clone(),
+                    range: Some(range.clone()).filter(|range| !range.is_full()),
+                    volume: *volume,
+                    fade_out: fade_out.clone(),
+                    parallel: *parallel,
+                    gap: gap.clone(),
+                    verbose: cli.verbose,
+                };
+
+                if send_daemon_request(&request, &mut out)? {
+                    return Ok(());
                 }
             }
+
+            let overrides = PlaybackOverrides {
+                duration: duration.clone(),
+                volume: *volume,
+                fade_out: fade_out.clone(),
+                range,
+            };
+            play_sound_items(sound_names, &overrides, *parallel, gap.as_ref(), cli.verbose, out)?;
         }
+        CliCommands::List => write_theme_sounds(out)?,
+        CliCommands::Config { command } => evaluate_config_command(command, out)?,
+        CliCommands::Cache { command } => evaluate_cache_command(command, out)?,
+        CliCommands::Daemon { request: None } => run_daemon(out)?,
+        CliCommands::Daemon { request: Some(request) } => {
+            if !send_daemon_request(request, out)? {
+                return Err(ThemerError::DaemonNotRunningError(get_socket_path()?));
+            }
+        }
+        #[cfg(feature = "dbus")]
+        CliCommands::Dbus => run_dbus_service(out)?,
+        CliCommands::CanberraGtkPlay(args) => evaluate_canberra_gtk_play(args, cli.verbose, out)?,
     }
 
     Ok(())
 }
+
+/// # Documentation
+/// Play the sound for the event described by the `canberra-gtk-play` arguments, `args.loop_count` times
+///
+/// # Errors
+/// Returns an error if `CanberraEvent::from_properties()` or `CanberraEvent::resolve()` fails
+/// Returns an error if the sound could not be played using `play_playback_steps()`
+/// Returns an error if `out` could not be written to
+fn evaluate_canberra_gtk_play<W: Write>(args: &CanberraGtkPlayArgs, verbose: bool, mut out: W) -> Result<(), ThemerError> {
+    let CanberraGtkPlayArgs {
+        id,
+        file,
+        description,
+        cache_control: _,
+        loop_count,
+        volume,
+        properties,
+    } = args;
+
+    // The flags take priority over the same properties set with --property
+    let mut properties = properties.iter().cloned().collect::<HashMap<_, _>>();
+    let flag_properties = [
+        (PROP_EVENT_ID, id),
+        (PROP_MEDIA_FILENAME, file),
+        (PROP_EVENT_DESCRIPTION, description),
+        (PROP_CANBERRA_VOLUME, volume),
+    ];
+    for (key, value) in flag_properties {
+        if let Some(value) = value {
+            properties.insert(key.to_string(), value.clone());
+        }
+    }
+
+    let event = CanberraEvent::from_properties(&properties)?;
+    let steps = event.resolve()?;
+
+    if verbose {
+        let sound_name = event.file.clone().or_else(|| event.event_id.clone()).unwrap_or_default();
+        write_resolved_steps(&mut out, sound_name, &steps)?;
+    }
+
+    for _ in 0..*loop_count {
+        play_playback_steps(&steps)?;
+    }
+
+    Ok(())
+}
+
+/// # Documentation
+/// Whether any CLI flags or environment variables change the config or theme, which the daemon wouldn't know about
+///
+/// # Errors
+/// Returns an error if `get_selected_config_path()` fails
+fn overrides_config(cli: &Cli) -> Result<bool, ThemerError> {
+    Ok(cli.theme.is_some()
+        || cli.random
+        || cli.locale.is_some()
+        || !cli.set.is_empty()
+        || get_selected_config_path()?.is_some()
+        || !get_env_config_overrides().is_empty())
+}
+
+/// # Errors
+/// Returns an error if the config file could not be found, written, read, or serialized
+/// Returns an error if the editor could not be run
+/// Returns an error if `validate_config_file()` fails
+/// Returns an error if `out` could not be written to
+fn evaluate_config_command<W: Write>(command: &ConfigCommands, mut out: W) -> Result<(), ThemerError> {
+    match command {
+        ConfigCommands::Init { force } => {
+            let config_path = init_config_file(*force)?;
+
+            writeln!(out, "Wrote the default config to '{config_path}'")?;
+        }
+        ConfigCommands::Path => {
+            let config_paths = get_config_file_layers()?;
+
+            if config_paths.is_empty() {
+                writeln!(out, "No config file was found, so the built-in config is used. Searched:")?;
+                get_config_search_paths()
+                    .iter()
+                    .try_for_each(|config_path| writeln!(out, "\t{config_path}"))?;
+            } else {
+                // The file which overrides the others is printed first
+                config_paths
+                    .iter()
+                    .rev()
+                    .try_for_each(|config_path| writeln!(out, "{config_path}"))?;
+            }
+        }
+        ConfigCommands::Show { origin: true, .. } => {
+            let (config, origins) = get_layered_toml_config()?;
+            let values = flatten_toml_table(&toml::Table::try_from(&config)?)
+                .into_iter()
+                .map(|(path, value)| (format!("{path} = {value}"), origins.get(&path)))
+                .collect::<Vec<_>>();
+
+            // Line up the origins after the longest value
+            let width = values.iter().map(|(value, _)| value.len()).max().unwrap_or_default();
+            values
+                .iter()
+                .try_for_each(|(value, origin)| writeln!(out, "{value:width$}  # {origin}"))?;
+        }
+        ConfigCommands::Show { resolved, .. } => {
+            let config = if *resolved {
+                get_resolved_toml_config()?
+            } else {
+                get_toml_config()?
+            };
+
+            write!(out, "{}", toml::to_string_pretty(&config)?)?;
+        }
+        ConfigCommands::Validate => validate_config_file(out)?,
+        ConfigCommands::Edit => {
+            // Create the config file from the defaults, if there isn't one to edit yet
+            let config_path = match get_config_path()? {
+                Some(config_path) => config_path,
+                None => init_config_file(false)?,
+            };
+
+            let editor = ["VISUAL", "EDITOR"]
+                .iter()
+                .find_map(|var| std::env::var(var).ok().filter(|editor| !editor.trim().is_empty()))
+                .unwrap_or_else(|| DEFAULT_EDITOR.to_string());
+
+            // The editor can include arguments (e.g: "code --wait")
+            let mut editor_args = editor.split_whitespace().map(ToString::to_string).collect::<Vec<_>>();
+            let program = editor_args.remove(0);
+            editor_args.push(config_path.clone());
+
+            let status = Command::new(&program)
+                .args(&editor_args)
+                .status()
+                .map_err(|e| ThemerError::CommandError {
+                    name: program.clone(),
+                    args: editor_args.clone(),
+                    e: e.to_string(),
+                })?;
+            if !status.success() {
+                return Err(ThemerError::CommandError {
+                    name: program,
+                    args: editor_args,
+                    e: format!("Editor exited with {status}"),
+                });
+            }
+
+            // Check the file which was just edited, even if the config was found elsewhere before it was created
+            select_config_path(Some(config_path))?;
+            validate_config_file(out)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// # Errors
+/// Returns an error if the cache file could not be found, written, or removed
+/// Returns an error if the selected theme could not be changed back after the cache is rebuilt
+/// Returns an error if `out` could not be written to
+fn evaluate_cache_command<W: Write>(command: &CacheCommands, mut out: W) -> Result<(), ThemerError> {
+    let cache_path = get_sound_cache_path()?;
+
+    match command {
+        CacheCommands::Rebuild => {
+            clear_sound_cache()?;
+
+            // Each theme is selected in turn, then the theme which was selected before is restored
+            let selected_theme = get_selected_theme()?;
+            for theme in get_toml_config()?.themes {
+                match select_theme_by_name(&theme.name).and_then(|()| cache_selected_theme_sounds()) {
+                    Ok(count) => writeln!(out, "Cached {count} sound(s) for theme '{}'", theme.name)?,
+                    Err(e) => writeln!(out, "Theme '{}' could not be cached:\t{e}", theme.name)?,
+                }
+            }
+            select_theme(selected_theme)?;
+
+            writeln!(out, "Rebuilt the cache:\t'{cache_path}'")?;
+        }
+        CacheCommands::Clear => {
+            if clear_sound_cache()? {
+                writeln!(out, "Removed the cache:\t'{cache_path}'")?;
+            } else {
+                writeln!(out, "No cache to remove:\t'{cache_path}'")?;
+            }
+        }
+        CacheCommands::Stats => {
+            let cache = lock_sound_cache()?;
+            if cache.themes.is_empty() {
+                writeln!(out, "No sounds are cached:\t'{cache_path}'")?;
+                return Ok(());
+            }
+
+            let size = fs::metadata(&cache_path).map(|metadata| metadata.len()).unwrap_or_default();
+            writeln!(out, "Cache ({size} bytes):\t'{cache_path}'")?;
+
+            for cached_theme in &cache.themes {
+                let (found, probed) = cached_theme.count_sounds();
+                let missing = cached_theme.sounds.len() - found;
+                let changed = cached_theme
+                    .directories
+                    .iter()
+                    .filter(|directory| directory.has_changed())
+                    .count();
+
+                let locale_str = cached_theme
+                    .locale
+                    .as_ref()
+                    .map(|locale| format!(" ({locale})"))
+                    .unwrap_or_default();
+                let status_str = if changed == 0 {
+                    String::from("up to date")
+                } else {
+                    format!("{changed} directory(s) changed, so it will be rebuilt when it is next used")
+                };
+
+                writeln!(
+                    out,
+                    "\tTheme '{}'{locale_str}:\t{found} sound(s) found, {missing} missing, {probed} probed, {status_str}",
+                    cached_theme.theme
+                )?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// # Documentation
+/// Write every problem found by `validate_config()` to `out`, followed by how many were found
+///
+/// # Errors
+/// Returns an error if `get_config_path()` or `validate_config()` fails
+/// Returns an error if the config file has any errors (Warnings are only written)
+/// Returns an error if `out` could not be written to
+fn validate_config_file<W: Write>(mut out: W) -> Result<(), ThemerError> {
+    let config_path = get_config_path()?.unwrap_or_else(|| BUILTIN_CONFIG_NAME.to_string());
+
+    let report = validate_config()?;
+    report.issues.iter().try_for_each(|issue| writeln!(out, "{issue}"))?;
+
+    let error_count = report.errors().count();
+    let warning_count = report.warnings().count();
+    if error_count > 0 {
+        return Err(ThemerError::ConfigInvalidError {
+            path: config_path,
+            count: error_count,
+        });
+    }
+
+    if warning_count > 0 {
+        writeln!(out, "Config is valid, with {warning_count} warning(s):\t'{config_path}'")?;
+    } else {
+        writeln!(out, "Config is valid:\t'{config_path}'")?;
+    }
+
+    Ok(())
+}
+
+/// # Documentation
+/// Write the name of every sound file in each directory of the selected theme, without their extensions
+///
+/// # Errors
+/// Returns an error if `get_selected_theme_paths()` or `get_selected_theme()` fails
+/// Returns an error if `fs::read_dir()` could not be called on a theme path
+/// Returns an error if `out` could not be written to
+pub fn write_theme_sounds<W: Write>(mut out: W) -> Result<(), ThemerError> {
+    // Get the theme path where the sound files are
+    let theme_paths = get_selected_theme_paths()?;
+
+    let sound_exts = get_selected_theme()?.sound_exts;
+    let sound_exts_str = sound_exts
+        .iter()
+        .map(|ext| format!("'.{ext}'"))
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    // List files in each of the folders
+    for (i, theme_path_str) in theme_paths.iter().enumerate() {
+        writeln!(out, "Listing {sound_exts_str} files in '{theme_path_str}':")?;
+
+        // Sounds which have a file for multiple extensions are only listed once
+        let mut listed_files = HashSet::new();
+
+        // Check this full path exists
+        let theme_path = Path::new(&theme_path_str);
+        // Get all the files in this folder and convert to their file names
+        fs::read_dir(theme_path)
+            .map_err(|e| ThemerError::FileReadWriteError(e.to_string()))?
+            .flatten()
+            .filter_map(|entry| {
+                let path = entry.path();
+
+                // Check if it is a file with one of the theme's extensions
+                if path.is_file()
+                    && let Some(ext) = path.extension()
+                    && sound_exts.iter().any(|sound_ext| ext == OsStr::new(sound_ext))
+                {
+                    // Get the file name without the extension, then convert to String
+                    path.file_stem().map(|file_name| file_name.display().to_string())
+                } else {
+                    None
+                }
+            })
+            .filter(|file| listed_files.insert(file.clone()))
+            .try_for_each(|file| writeln!(out, "\t{file}"))?;
+
+        // Add an extra newline between different path directories
+        if i < theme_paths.len() - 1 {
+            writeln!(out)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// # Documentation
+/// Resolve each of `sound_items` using `overrides`, where an item's own duration takes priority over `overrides.duration`,
+/// then play them one after another with `gap` between each one, or all at once if `parallel` is set (Where the steps of
+/// each sequence are still played in order)
+///
+/// # Errors
+/// Returns an error if `gap` is a percentage
+/// Returns an error if any of the sounds could not be resolved using `resolve_sound_steps()`
+/// Returns an error if the sounds could not be played using `play_playback_steps()` or `play_parallel_playback_steps()`
+/// Returns an error if `out` could not be written to
+pub fn play_sound_items<W: Write>(
+    sound_items: &[SoundItem],
+    overrides: &PlaybackOverrides,
+    parallel: bool,
+    gap: Option<&PlaybackDuration>,
+    verbose: bool,
+    mut out: W,
+) -> Result<(), ThemerError> {
+    let gap = gap.map(PlaybackDuration::to_gap).transpose()?;
+
+    // Every sound is found before any are played, so a missing sound doesn't cut the sequence short
+    let mut sequences = Vec::new();
+    for sound_item in sound_items {
+        let steps = resolve_sound_steps(
+            &sound_item.sound_name,
+            &PlaybackOverrides {
+                duration: sound_item.duration.clone().or_else(|| overrides.duration.clone()),
+                ..overrides.clone()
+            },
+        )?;
+
+        if verbose {
+            write_resolved_steps(&mut out, &sound_item.sound_name, &steps)?;
+        }
+
+        sequences.push(steps);
+    }
+
+    // The mapping is written before the sounds are played, so it is shown straight away
+    out.flush()?;
+
+    if parallel {
+        return play_parallel_playback_steps(&sequences);
+    }
+
+    let mut steps = Vec::new();
+    for (i, sequence) in sequences.into_iter().enumerate() {
+        if i > 0
+            && let Some(gap) = gap
+        {
+            steps.push(PlaybackStep::Gap(gap));
+        }
+        steps.extend(sequence);
+    }
+
+    play_playback_steps(&steps)
+}
+
+/// # Documentation
+/// Write the aliases and mapping which `sound_name` passed through, then the sound file and options of each step, along
+/// with any gaps between them
+///
+/// # Errors
+/// Returns an error if `get_selected_theme()` fails
+/// Returns an error if `out` could not be written to
+pub fn write_resolved_steps<W: Write, S: AsRef<str>>(
+    mut out: W,
+    sound_name: S,
+    steps: &[PlaybackStep],
+) -> Result<(), ThemerError> {
+    let theme = get_selected_theme()?;
+
+    if let Some(alias_chain) = theme.mapping.get_alias_chain(sound_name.as_ref()) {
+        let alias_chain_str = alias_chain
+            .iter()
+            .map(|key| format!("'{key}'"))
+            .collect::<Vec<_>>()
+            .join(" --> ");
+        writeln!(out, "Resolved alias {alias_chain_str}")?;
+    }
+
+    if let Some(rule) = theme.mapping.get_matching_rule(sound_name.as_ref()) {
+        writeln!(out, "Matched rule '{}'", rule.key)?;
+    }
+
+    if let Some(mapping_entry) = get_mapping_entry(&theme, sound_name.as_ref()) {
+        writeln!(out, "Mapped '{}' to '{mapping_entry}'", sound_name.as_ref())?;
+    }
+
+    for step in steps {
+        match step {
+            PlaybackStep::Sound(sound) => write_playing_sound(&mut out, sound)?,
+            PlaybackStep::Gap(gap) => writeln!(out, "Waiting {:.3}s", gap.as_secs_f32())?,
+        }
+    }
+
+    Ok(())
+}
+
+// Write the sound file and the options it is played with
+fn write_playing_sound<W: Write>(mut out: W, sound: &ResolvedSound) -> Result<(), ThemerError> {
+    let options = sound.options;
+    let mut options_strs = vec![format!("volume {}", options.volume)];
+    if let Some(start) = options.start {
+        options_strs.push(format!("start {:.3}s", start.as_secs_f32()));
+    }
+    if let Some(duration) = options.duration {
+        options_strs.push(format!("duration {:.3}s", duration.as_secs_f32()));
+    }
+    if let Some(fade_out) = options.fade_out {
+        options_strs.push(format!("fade-out {:.3}s", fade_out.as_secs_f32()));
+    }
+
+    writeln!(out, "Playing '{}' ({})", sound.path, options_strs.join(", "))?;
+
+    Ok(())
+}
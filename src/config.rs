@@ -1,14 +1,26 @@
-use std::{fs, path::Path, sync::LazyLock};
+use std::{collections::HashSet, fmt, fs, iter, path::Path, str::FromStr, sync::Mutex};
 
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
+use strum::Display;
+use toml::{
+    Spanned, Table, Value,
+    de::{DeTable, DeValue, ValueDeserializer},
+};
 
 use crate::{
+    duration::PlaybackDuration,
     error::ThemerError,
+    layer::{ConfigOrigin, ConfigOrigins, ConfigOverride, apply_config_override, get_env_config_overrides, merge_toml_table},
     mapping::Mapping,
-    theme::{DEFAULT_SOUND_DIRS, DEFAULT_SOUND_EXT, DEFAULT_THEME_NAME, Theme},
+    theme::{
+        DEFAULT_SOUND_DIRS, DEFAULT_SOUND_EXTS, DEFAULT_THEME_NAME, Theme, check_config_aliases, get_theme_from_name,
+        validate_theme,
+    },
+    volume::Volume,
+    xdg::{get_config_dirs, get_config_home, get_env_path, get_home_dir},
 };
 
-#[derive(Deserialize, Clone, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct TOMLConfig {
     /// # Documentation
     /// The name of the folder which holds the sound theme
@@ -20,74 +32,600 @@ pub struct TOMLConfig {
     /// The configuration for each theme, defined by their name
     #[serde(default = "get_default_config_themes")]
     pub themes: Vec<Theme>,
+
+    /// # Documentation
+    /// The mapping which every theme inherits, layered over the default mapping
+    #[serde(default, skip_serializing_if = "Mapping::is_empty_layer")]
+    pub mapping: Mapping,
+
+    /// # Documentation
+    /// Extra directories which contain sound themes, searched before the XDG data directories
+    #[serde(default)]
+    pub search_paths: Vec<String>,
+
+    /// # Documentation
+    /// The volume of every sound, multiplied by the theme, mapping, and `--volume` volumes
+    #[serde(default)]
+    pub volume: Volume,
+
+    /// # Documentation
+    /// How long a sound fades out for when it is cut short by a duration (Uses the same format as `--duration`)
+    #[serde(default = "get_default_config_fade_out")]
+    pub fade_out: String,
+
+    /// # Documentation
+    /// The program which is used to play the sounds
+    #[serde(default)]
+    pub player: PlayerConfig,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct PlayerConfig {
+    /// # Documentation
+    /// The backend which plays the sounds, `auto` uses the first player found in `$PATH`
+    #[serde(default)]
+    pub backend: PlayerBackendKind,
+
+    /// # Documentation
+    /// A custom command to play the sounds, where `{path}`, `{volume}`, `{volume_percent}`, and `{duration}` are replaced
+    /// with their values
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub command: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum PlayerBackendKind {
+    #[default]
+    Auto,
+    PwPlay,
+    Paplay,
+    Aplay,
+    Ffplay,
+    Native,
+    Command,
+}
+
+/// # Documentation
+/// How serious a problem found by `validate_config()` is
+#[derive(Debug, Display, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[strum(serialize_all = "lowercase")]
+pub enum ValidationSeverity {
+    /// Some sounds may not play as expected
+    Warning,
+    /// The config can't be used, or some sounds can't be played
+    Error,
+}
+
+/// # Documentation
+/// A single problem found by `validate_config()`, with the theme it was found in
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ValidationIssue {
+    pub severity: ValidationSeverity,
+    pub theme: Option<String>,
+    pub message: String,
+}
+
+impl fmt::Display for ValidationIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.theme {
+            Some(theme) => write!(f, "{} in theme '{theme}':\t{}", self.severity, self.message),
+            None => write!(f, "{}:\t{}", self.severity, self.message),
+        }
+    }
+}
+
+/// # Documentation
+/// Every problem found by `validate_config()`, in the order they were found
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ValidationReport {
+    pub issues: Vec<ValidationIssue>,
+}
+
+impl ValidationReport {
+    /// # Documentation
+    /// Add a problem to the report, where `theme` is the theme it was found in (if any)
+    pub fn push<S: AsRef<str>>(&mut self, severity: ValidationSeverity, theme: Option<&str>, message: S) {
+        self.issues.push(ValidationIssue {
+            severity,
+            theme: theme.map(ToString::to_string),
+            message: message.as_ref().to_string(),
+        });
+    }
+
+    /// # Documentation
+    /// Get the problems which stop the config, or some sounds, from being used
+    pub fn errors(&self) -> impl Iterator<Item = &ValidationIssue> {
+        self.issues.iter().filter(|issue| issue.severity == ValidationSeverity::Error)
+    }
+
+    /// # Documentation
+    /// Get the problems which may cause sounds to not play as expected
+    pub fn warnings(&self) -> impl Iterator<Item = &ValidationIssue> {
+        self.issues
+            .iter()
+            .filter(|issue| issue.severity == ValidationSeverity::Warning)
+    }
+
+    /// # Documentation
+    /// Whether the report contains any errors
+    #[must_use]
+    pub fn has_errors(&self) -> bool {
+        self.errors().next().is_some()
+    }
 }
 
 fn get_default_config_theme_name() -> String {
     String::from(DEFAULT_THEME_NAME)
 }
 
+fn get_default_config_fade_out() -> String {
+    String::from(DEFAULT_FADE_OUT)
+}
+
 fn get_default_config_themes() -> Vec<Theme> {
     vec![Theme::new(
         DEFAULT_THEME_NAME,
-        DEFAULT_SOUND_EXT,
+        DEFAULT_SOUND_EXTS,
         DEFAULT_SOUND_DIRS,
         Mapping::default(),
     )]
 }
 
+const DEFAULT_FADE_OUT: &str = "10ms";
 const DEFAULT_CONFIG_PATH: &str = "/etc/sound_themer/config.toml";
 const CONFIG_PATH_SHORT: &str = "sound_themer/config.toml";
 
-static CONFIG: LazyLock<TOMLConfig> = LazyLock::new(init_toml_config);
+/// # Documentation
+/// The environment variable which sets the config file, instead of searching for it
+pub const CONFIG_PATH_ENV_VAR: &str = "SOUND_THEMER_CONFIG";
+
+/// # Documentation
+/// The default config, which is used when no config file could be found
+pub const BUILTIN_CONFIG_STR: &str = include_str!("../default/config.toml");
+pub const BUILTIN_CONFIG_NAME: &str = "<built-in config>";
 
+// Used in errors which only appear once every layer has been merged
+const LAYERED_CONFIG_NAME: &str = "<layered config>";
+
+// Only set once the config has been read successfully, so a failed read is tried again
+static CONFIG: Mutex<Option<TOMLConfig>> = Mutex::new(None);
+
+static SELECTED_CONFIG_PATH: Mutex<Option<String>> = Mutex::new(None);
+
+// Set by CLI flags, and layered over every other layer
+static CONFIG_OVERRIDES: Mutex<Vec<ConfigOverride>> = Mutex::new(Vec::new());
+
+/// # Errors
+/// Returns an error if `get_layered_toml_config()` fails
 #[doc(hidden)]
-#[must_use]
-pub fn init_toml_config() -> TOMLConfig {
-    let config_home = get_config_home_dir();
+pub fn init_toml_config() -> Result<TOMLConfig, ThemerError> {
+    Ok(get_layered_toml_config()?.0)
+}
+
+/// # Documentation
+/// Read every layer of the config and merge them, returning the config along with the layer which set each value.
+/// The layers are the built-in config, the files from `get_config_file_layers()`, the `SOUND_THEMER_*` environment
+/// variables, then the overrides from `select_config_overrides()`, where each layer overrides the layers before it
+///
+/// # Errors
+/// Returns an error if `get_config_file_layers()` fails
+/// Returns an error if any of the config files could not be read or parsed
+/// Returns an error if any of the overrides could not be applied, or give a value of the wrong type
+/// Returns an error if `CONFIG_OVERRIDES` couldn't be locked
+/// Returns an error if `check_config_aliases()` fails
+pub fn get_layered_toml_config() -> Result<(TOMLConfig, ConfigOrigins), ThemerError> {
+    let mut table = Table::new();
+    let mut origins = ConfigOrigins::default();
+
+    let builtin_table = parse_toml_table(BUILTIN_CONFIG_NAME, BUILTIN_CONFIG_STR)?;
+    merge_toml_table(&mut table, &builtin_table, &ConfigOrigin::BuiltIn, &mut origins);
 
-    get_toml_config_from_file(format!("{config_home}/{CONFIG_PATH_SHORT}"))
+    for config_path in get_config_file_layers()? {
+        let config_str =
+            fs::read_to_string(&config_path).map_err(|e| ThemerError::FileReadWriteError(format!("'{config_path}': {e}")))?;
+
+        let file_table = parse_toml_table(&config_path, &config_str)?;
+        merge_toml_table(&mut table, &file_table, &ConfigOrigin::File(config_path), &mut origins);
+    }
+
+    let cli_overrides = CONFIG_OVERRIDES
+        .lock()
+        .map_err(|e| ThemerError::MutexLockError(e.to_string()))?
+        .clone();
+
+    for config_override in get_env_config_overrides().iter().chain(&cli_overrides) {
+        apply_config_override(&mut table, config_override, &mut origins)?;
+
+        // Each override is checked on its own, so a value of the wrong type is reported with the override which set it
+        Value::Table(table.clone())
+            .try_into::<TOMLConfig>()
+            .map_err(|e| ThemerError::ConfigOverrideError {
+                key: format!("{} (from {})", config_override.path, config_override.origin),
+                e: e.message().to_string(),
+            })?;
+    }
+
+    let config = Value::Table(table)
+        .try_into()
+        .map_err(|e: toml::de::Error| ThemerError::ConfigParseError {
+            path: LAYERED_CONFIG_NAME.to_string(),
+            span: None,
+            e: e.to_string(),
+        })?;
+
+    check_config_aliases(&config)?;
+
+    Ok((config, origins))
 }
 
+/// # Documentation
+/// Set the overrides which are layered over every other layer of the config (e.g: from `--set`).
+/// The config is read again the next time it is used
+///
+/// # Errors
+/// Returns an error if `CONFIG_OVERRIDES` or `CONFIG` couldn't be locked
+pub fn select_config_overrides(config_overrides: Vec<ConfigOverride>) -> Result<(), ThemerError> {
+    *CONFIG_OVERRIDES
+        .lock()
+        .map_err(|e| ThemerError::MutexLockError(e.to_string()))? = config_overrides;
+
+    *CONFIG.lock().map_err(|e| ThemerError::MutexLockError(e.to_string()))? = None;
+
+    Ok(())
+}
+
+/// # Documentation
+/// Get the config files which are layered over the built-in config, in order of priority from lowest to highest.
+/// This is `/etc/sound_themer/config.toml`, each entry of `$XDG_CONFIG_DIRS` from last to first, then the user's config
+/// file from `$XDG_CONFIG_HOME` or `$HOME/.config`, skipping any files which don't exist.
+/// If the config file is overridden by `get_selected_config_path()`, only that file is used
+///
+/// # Errors
+/// Returns an error if `get_selected_config_path()` fails
+pub fn get_config_file_layers() -> Result<Vec<String>, ThemerError> {
+    // An overridden config file is always used, so it is an error if it is missing
+    if let Some(config_path) = get_selected_config_path()? {
+        return Ok(vec![config_path]);
+    }
+
+    let to_config_path = |config_dir: String| format!("{}/{CONFIG_PATH_SHORT}", config_dir.trim_end_matches('/'));
+
+    let user_config_path = get_env_path("XDG_CONFIG_HOME")
+        .into_iter()
+        .chain(get_home_dir().map(|home| format!("{home}/.config")))
+        .map(to_config_path)
+        .find(|config_path| Path::new(config_path).is_file());
+
+    // Remove any duplicates, keeping the occurrence with the highest priority
+    let mut seen = HashSet::new();
+    let mut config_paths = user_config_path
+        .into_iter()
+        .chain(get_config_dirs().into_iter().map(to_config_path))
+        .chain(iter::once(DEFAULT_CONFIG_PATH.to_string()))
+        .filter(|config_path| Path::new(config_path).is_file() && seen.insert(config_path.clone()))
+        .collect::<Vec<_>>();
+    config_paths.reverse();
+
+    Ok(config_paths)
+}
+
+/// # Errors
+/// Returns an error if neither `$XDG_CONFIG_HOME` nor `$HOME` are set
 #[doc(hidden)]
+pub fn get_config_home_dir() -> Result<String, ThemerError> {
+    get_config_home().ok_or_else(|| ThemerError::EnvironmentVarError("$XDG_CONFIG_HOME".to_string()))
+}
+
+/// # Documentation
+/// Override the config file which is searched for, `None` removes the override.
+/// The config is read again the next time it is used
+///
+/// # Errors
+/// Returns an error if `SELECTED_CONFIG_PATH` or `CONFIG` couldn't be locked
+pub fn select_config_path(config_path: Option<String>) -> Result<(), ThemerError> {
+    *SELECTED_CONFIG_PATH
+        .lock()
+        .map_err(|e| ThemerError::MutexLockError(e.to_string()))? = config_path;
+
+    *CONFIG.lock().map_err(|e| ThemerError::MutexLockError(e.to_string()))? = None;
+
+    Ok(())
+}
+
+/// # Documentation
+/// Get the ordered list of files which the config is searched for in.
+/// This is `$XDG_CONFIG_HOME`, `$HOME/.config`, then each entry of `$XDG_CONFIG_DIRS`, followed by `/etc/sound_themer/config.toml`
 #[must_use]
-pub fn get_config_home_dir() -> String {
-    // Get the $XDG_CONFIG_HOME directory
-    let home_os_str = std::env::var_os("XDG_CONFIG_HOME")
-        .unwrap_or_else(|| panic!("{}", ThemerError::EnvironmentVarError("$XDG_CONFIG_HOME".to_string())));
+pub fn get_config_search_paths() -> Vec<String> {
+    let config_dirs = get_env_path("XDG_CONFIG_HOME")
+        .into_iter()
+        .chain(get_home_dir().map(|home| format!("{home}/.config")))
+        .chain(get_config_dirs())
+        .map(|config_dir| format!("{}/{CONFIG_PATH_SHORT}", config_dir.trim_end_matches('/')));
+
+    // Remove any duplicates, keeping the first occurrence of each path
+    let mut seen = HashSet::new();
+    config_dirs
+        .chain(iter::once(DEFAULT_CONFIG_PATH.to_string()))
+        .filter(|config_path| seen.insert(config_path.clone()))
+        .collect()
+}
+
+/// # Documentation
+/// Get the config file set by `select_config_path()`, otherwise the file set by `$SOUND_THEMER_CONFIG`
+///
+/// # Errors
+/// Returns an error if `SELECTED_CONFIG_PATH` couldn't be locked
+pub fn get_selected_config_path() -> Result<Option<String>, ThemerError> {
+    let selected_config_path = SELECTED_CONFIG_PATH
+        .lock()
+        .map_err(|e| ThemerError::MutexLockError(e.to_string()))?
+        .clone();
+
+    Ok(selected_config_path.or_else(|| std::env::var(CONFIG_PATH_ENV_VAR).ok().filter(|path| !path.is_empty())))
+}
+
+/// # Documentation
+/// Get the config file set by `get_selected_config_path()`, otherwise the first file in the search path which exists.
+/// Gives `None` if no config file could be found, so the built-in config should be used
+///
+/// # Errors
+/// Returns an error if `get_selected_config_path()` fails
+pub fn get_config_path() -> Result<Option<String>, ThemerError> {
+    // An overridden config file is always used, so it is an error if it is missing
+    if let Some(config_path) = get_selected_config_path()? {
+        return Ok(Some(config_path));
+    }
+
+    Ok(get_config_search_paths()
+        .into_iter()
+        .find(|config_path| Path::new(config_path).is_file()))
+}
 
-    // Convert $XDG_CONFIG_HOME to &str
-    home_os_str
-        .to_str()
-        .unwrap_or_else(|| panic!("{}", ThemerError::EnvVarToStrError("$XDG_CONFIG_HOME".to_string())))
-        .to_string()
+/// # Documentation
+/// Get the config file which is written by `init_config_file()`, which is the overridden config file if there is one,
+/// otherwise `$XDG_CONFIG_HOME/sound_themer/config.toml`
+///
+/// # Errors
+/// Returns an error if `get_selected_config_path()` fails
+/// Returns an error if `get_config_home_dir()` fails
+pub fn get_user_config_path() -> Result<String, ThemerError> {
+    match get_selected_config_path()? {
+        Some(config_path) => Ok(config_path),
+        None => Ok(format!("{}/{CONFIG_PATH_SHORT}", get_config_home_dir()?)),
+    }
 }
 
-fn get_toml_config_from_file<S: AsRef<str>>(file_path: S) -> TOMLConfig {
-    let config_path = Path::new(file_path.as_ref());
+/// # Documentation
+/// Write the built-in config to `get_user_config_path()`, returning the path of the file which was written
+///
+/// # Errors
+/// Returns an error if `get_user_config_path()` fails
+/// Returns an error if the config file already exists, and `force` isn't set
+/// Returns an error if the config file, or its parent folders, could not be written
+pub fn init_config_file(force: bool) -> Result<String, ThemerError> {
+    let config_path_str = get_user_config_path()?;
+    let config_path = Path::new(&config_path_str);
 
-    // If the config_path doesn't point to any file, copy it from /etc/
-    if !config_path.exists() {
-        // Create the config_path parent folders
-        fs::create_dir_all(config_path.parent().unwrap_or_else(|| {
-            panic!(
-                "{}",
-                ThemerError::PathCreateError(String::from("Could get parent of `config_path`"))
-            )
-        }))
-        .unwrap_or_else(|e| panic!("{}", ThemerError::PathCreateError(e.to_string())));
+    if config_path.exists() && !force {
+        return Err(ThemerError::ConfigExistsError(config_path_str));
+    }
 
-        // Copy the default config from /etc/
-        fs::copy(DEFAULT_CONFIG_PATH, config_path)
-            .unwrap_or_else(|e| panic!("{}", ThemerError::FileReadWriteError(e.to_string())));
+    // Create the config_path parent folders
+    if let Some(parent) = config_path.parent() {
+        fs::create_dir_all(parent).map_err(|e| ThemerError::PathCreateError(e.to_string()))?;
     }
 
-    // Read the config file as a String (Converting Error to DaemonError::PathRwError)
-    let config = fs::read_to_string(config_path).unwrap_or_else(|e| panic!("{}", ThemerError::FileReadWriteError(e.to_string())));
+    fs::write(config_path, BUILTIN_CONFIG_STR)
+        .map_err(|e| ThemerError::FileReadWriteError(format!("'{config_path_str}': {e}")))?;
 
-    // Convert the text in the config file to a Config struct using TOML
-    toml::from_str(config.as_str()).unwrap_or_else(|e| panic!("{}", ThemerError::TomlReadError(e)))
+    Ok(config_path_str)
 }
 
-pub fn get_toml_config() -> TOMLConfig {
-    CONFIG.clone()
+/// # Documentation
+/// Get the config with the default mapping filled in, and each theme replaced by the theme which is used when it is
+/// selected (With every layer of its mapping, and the values from its `index.theme`)
+///
+/// # Errors
+/// Returns an error if `get_toml_config()` fails
+/// Returns an error if any of the themes could not be resolved by `get_theme_from_name()`
+pub fn get_resolved_toml_config() -> Result<TOMLConfig, ThemerError> {
+    let mut config = get_toml_config()?;
+
+    config.themes = config
+        .themes
+        .iter()
+        .map(|theme| get_theme_from_name(&theme.name))
+        .collect::<Result<_, _>>()?;
+    config.mapping = config.mapping.layered_over(&Mapping::builtin());
+
+    Ok(config)
+}
+
+/// # Documentation
+/// Find every problem in the text of a config file, where `file_path` is only used in errors.
+/// Each top-level value and each theme is read on its own, so one mistake doesn't hide the mistakes after it
+#[must_use]
+pub fn get_toml_config_errors<P: AsRef<str>, S: AsRef<str>>(file_path: P, config: S) -> Vec<ThemerError> {
+    let to_parse_error = |mut e: toml::de::Error| {
+        // Errors from parts of the document need the text to show their line and column
+        e.set_input(Some(config.as_ref()));
+
+        ThemerError::ConfigParseError {
+            path: file_path.as_ref().to_string(),
+            span: e.span(),
+            e: e.to_string(),
+        }
+    };
+
+    let (root, syntax_errors) = DeTable::parse_recoverable(config.as_ref());
+    if !syntax_errors.is_empty() {
+        return syntax_errors.into_iter().map(to_parse_error).collect();
+    }
+
+    let root_span = root.span();
+    let mut errors = Vec::new();
+    for (key, value) in root.into_inner() {
+        if key.get_ref() == "themes"
+            && let DeValue::Array(themes) = value.get_ref()
+        {
+            errors.extend(
+                themes
+                    .iter()
+                    .filter_map(|theme| Theme::deserialize(ValueDeserializer::from(theme.clone())).err())
+                    .map(to_parse_error),
+            );
+        } else {
+            let mut table = DeTable::new();
+            table.insert(key, value);
+
+            if let Err(e) = TOMLConfig::deserialize(toml::de::Deserializer::from(Spanned::new(root_span.clone(), table))) {
+                errors.push(to_parse_error(e));
+            }
+        }
+    }
+
+    // Anything which is only wrong when the values are read together is still found
+    if errors.is_empty()
+        && let Err(e) = parse_toml_config(file_path.as_ref(), config.as_ref())
+    {
+        errors.push(e);
+    }
+
+    errors
+}
+
+/// # Documentation
+/// Check the config for every problem which would stop a sound from playing as expected.
+/// If any of the config files can't be parsed, or the overrides can't be applied, only those errors are reported,
+/// otherwise the selected theme and every theme in the layered config are checked with `validate_theme()`, along with
+/// the values which aren't checked when the config is read
+///
+/// # Errors
+/// Returns an error if `get_config_file_layers()` fails
+/// Returns an error if any of the config files could not be read
+pub fn validate_config() -> Result<ValidationReport, ThemerError> {
+    let mut config_files = vec![(BUILTIN_CONFIG_NAME.to_string(), BUILTIN_CONFIG_STR.to_string())];
+    for config_path in get_config_file_layers()? {
+        let config_str =
+            fs::read_to_string(&config_path).map_err(|e| ThemerError::FileReadWriteError(format!("'{config_path}': {e}")))?;
+
+        config_files.push((config_path, config_str));
+    }
+
+    let mut report = ValidationReport::default();
+
+    for (config_path, config_str) in &config_files {
+        for e in get_toml_config_errors(config_path, config_str) {
+            report.push(ValidationSeverity::Error, None, e.to_string());
+        }
+    }
+
+    if report.has_errors() {
+        return Ok(report);
+    }
+
+    let config = match get_layered_toml_config() {
+        Ok((config, _)) => config,
+        Err(e) => {
+            report.push(ValidationSeverity::Error, None, e.to_string());
+
+            return Ok(report);
+        }
+    };
+
+    if let Err(e) = PlaybackDuration::from_str(&config.fade_out) {
+        report.push(
+            ValidationSeverity::Error,
+            None,
+            format!("The global `fade_out` is invalid:\t{e}"),
+        );
+    }
+
+    // Themes with the same name hide each other, since only the last one can be selected
+    let mut theme_names = Vec::new();
+    for theme in &config.themes {
+        if theme_names.contains(&theme.name) {
+            report.push(
+                ValidationSeverity::Error,
+                Some(&theme.name),
+                "Theme is defined more than once, so only its last definition is used",
+            );
+        } else {
+            theme_names.push(theme.name.clone());
+        }
+    }
+
+    // The selected theme can also be a theme which only has an `index.theme`
+    if !theme_names.contains(&config.theme_name) {
+        theme_names.insert(0, config.theme_name.clone());
+    }
+
+    for theme_name in theme_names {
+        match get_theme_from_name(&theme_name) {
+            Ok(theme) => report.issues.extend(validate_theme(&theme).issues),
+            Err(e) => report.push(
+                ValidationSeverity::Error,
+                Some(&theme_name),
+                format!("Theme could not be loaded:\t{e}"),
+            ),
+        }
+    }
+
+    Ok(report)
+}
+
+/// # Documentation
+/// Convert the text of a config file to a table which can be layered, where `file_path` is only used in errors
+///
+/// # Errors
+/// Returns an error if `parse_toml_config()` fails, so that each file is checked on its own before it is layered
+fn parse_toml_table<P: AsRef<str>, S: AsRef<str>>(file_path: P, config: S) -> Result<Table, ThemerError> {
+    parse_toml_config(file_path.as_ref(), config.as_ref())?;
+
+    toml::from_str(config.as_ref()).map_err(|e| ThemerError::ConfigParseError {
+        path: file_path.as_ref().to_string(),
+        span: e.span(),
+        e: e.to_string(),
+    })
+}
+
+/// # Errors
+/// Returns an error if the built-in config could not be parsed
+pub fn get_builtin_toml_config() -> Result<TOMLConfig, ThemerError> {
+    parse_toml_config(BUILTIN_CONFIG_NAME, BUILTIN_CONFIG_STR)
+}
+
+/// # Documentation
+/// Convert the text of a config file to a `TOMLConfig`, where `file_path` is only used in errors
+///
+/// # Errors
+/// Returns an error if the text is not valid TOML, or doesn't match the layout of `TOMLConfig`
+pub fn parse_toml_config<P: AsRef<str>, S: AsRef<str>>(file_path: P, config: S) -> Result<TOMLConfig, ThemerError> {
+    toml::from_str(config.as_ref()).map_err(|e| ThemerError::ConfigParseError {
+        path: file_path.as_ref().to_string(),
+        span: e.span(),
+        e: e.to_string(),
+    })
+}
+
+/// # Errors
+/// Returns an error if `CONFIG` couldn't be locked
+/// Returns an error if the config has not been read yet, and `init_toml_config()` fails
+pub fn get_toml_config() -> Result<TOMLConfig, ThemerError> {
+    let config = CONFIG.lock().map_err(|e| ThemerError::MutexLockError(e.to_string()))?.clone();
+
+    if let Some(config) = config {
+        return Ok(config);
+    }
+
+    let config = init_toml_config()?;
+
+    // Keep the config read by another thread while this one was reading, if there is one
+    Ok(CONFIG
+        .lock()
+        .map_err(|e| ThemerError::MutexLockError(e.to_string()))?
+        .get_or_insert(config)
+        .clone())
 }
@@ -0,0 +1,462 @@
+use std::{
+    fs,
+    io::{BufRead, BufReader, Write},
+    os::unix::net::{UnixListener, UnixStream},
+    path::Path,
+    thread,
+};
+
+use clap::{Parser, Subcommand};
+
+use crate::{
+    cli::{play_sound_items, write_theme_sounds},
+    config::get_toml_config,
+    duration::{PlaybackDuration, PlaybackRange},
+    error::ThemerError,
+    sound::{PlaybackOverrides, SoundItem, split_command_template, stop_sounds},
+    theme::{get_selected_theme, select_theme_by_name},
+    volume::Volume,
+    xdg::get_private_runtime_dir,
+};
+
+const SOCKET_FILE_NAME: &str = "sound_themer.sock";
+
+// Each line of a response starts with one of these, where the last line is either `RESPONSE_OK` or `RESPONSE_FAILED`
+const RESPONSE_DATA: &str = "data";
+const RESPONSE_ERROR: &str = "error";
+const RESPONSE_OK: &str = "ok";
+const RESPONSE_FAILED: &str = "failed";
+
+/// # Documentation
+/// A single line sent to the daemon, which is written in the same way as the CLI arguments (e.g: `play bell --volume 0.5`)
+#[derive(Parser, Debug)]
+#[command(name = "sound_themer daemon", no_binary_name = true)]
+#[command(disable_help_flag = true, disable_help_subcommand = true, disable_version_flag = true)]
+struct DaemonRequestLine {
+    #[command(subcommand)]
+    request: DaemonRequest,
+}
+
+/// # Documentation
+/// A request which can be sent to the daemon
+#[derive(Subcommand, Debug, Clone)]
+pub enum DaemonRequest {
+    /// A request to play sounds, which is answered once they have finished playing
+    #[command(about = "Play sounds using the daemon's theme, one after another or all at once.")]
+    Play {
+        /// The names of the sounds which will be played (from the daemon's theme), which can each set their own duration
+        /// (e.g: complete, message@250ms, bell@50%)
+        #[arg(required = true, num_args = 1..)]
+        sound_names: Vec<SoundItem>,
+
+        /// Limit the playback length of each sound which doesn't set its own, from where it starts (e.g: 250ms, 1s, 50%, 2)
+        #[arg(short, long, value_parser)]
+        duration: Option<PlaybackDuration>,
+
+        /// Play a section of the sound, where either side can be left empty (e.g: 250ms..1s, 20%..80%, ..-200ms)
+        #[arg(long, value_parser, allow_hyphen_values(true))]
+        range: Option<PlaybackRange>,
+
+        /// Set the volume of the sound, multiplied by the config volumes (e.g: 0.8, 80%, -3dB, up to 1.5 or 150%)
+        #[arg(short, long, value_parser, allow_hyphen_values(true))]
+        volume: Option<Volume>,
+
+        /// Fade out the end of the sound over this duration, instead of the config's fade-out (e.g: 30ms, 0.1s, 5%)
+        #[arg(short, long, value_parser)]
+        fade_out: Option<PlaybackDuration>,
+
+        /// Play every sound at once, instead of one after another
+        #[arg(short, long, conflicts_with("gap"))]
+        parallel: bool,
+
+        /// Wait this long between each sound (e.g: 100ms, 0.5s)
+        #[arg(short, long, value_parser)]
+        gap: Option<PlaybackDuration>,
+
+        /// Send back how the sound name is mapped to a sound file before it is played
+        #[arg(long)]
+        verbose: bool,
+    },
+    /// A request to stop every sound which the daemon is playing
+    #[command(about = "Stop every sound which the daemon is playing.")]
+    Stop,
+    /// A request to list the sounds in the daemon's theme
+    #[command(about = "List the sounds in the daemon's theme.")]
+    List,
+    /// A request to change the daemon's theme
+    #[command(about = "Change the theme which the daemon plays sounds from.")]
+    SetTheme {
+        /// The name of the theme to select
+        theme_name: String,
+    },
+}
+
+impl DaemonRequest {
+    /// # Documentation
+    /// Write the request as a single line, which is read by the daemon using `DaemonRequest::parse_line()`
+    #[must_use]
+    pub fn to_line(&self) -> String {
+        let mut args = Vec::new();
+
+        match self {
+            Self::Play {
+                sound_names,
+                duration,
+                range,
+                volume,
+                fade_out,
+                parallel,
+                gap,
+                verbose,
+            } => {
+                args.push(String::from("play"));
+
+                let options = [
+                    ("--duration", duration.as_ref().map(ToString::to_string)),
+                    ("--range", range.as_ref().map(ToString::to_string)),
+                    ("--volume", volume.as_ref().map(ToString::to_string)),
+                    ("--fade-out", fade_out.as_ref().map(ToString::to_string)),
+                    ("--gap", gap.as_ref().map(ToString::to_string)),
+                ];
+                for (flag, value) in options {
+                    if let Some(value) = value {
+                        args.extend([flag.to_string(), value]);
+                    }
+                }
+
+                let switches = [("--parallel", *parallel), ("--verbose", *verbose)];
+                args.extend(
+                    switches
+                        .into_iter()
+                        .filter(|(_, is_set)| *is_set)
+                        .map(|(switch, _)| switch.to_string()),
+                );
+
+                // The names come after `--`, so a name starting with `-` isn't read as an option
+                args.push(String::from("--"));
+                args.extend(sound_names.iter().map(ToString::to_string));
+            }
+            Self::Stop => args.push(String::from("stop")),
+            Self::List => args.push(String::from("list")),
+            Self::SetTheme { theme_name } => {
+                args.extend([String::from("set-theme"), String::from("--"), theme_name.clone()]);
+            }
+        }
+
+        args.iter().map(|arg| quote_arg(arg)).collect::<Vec<_>>().join(" ")
+    }
+
+    /// # Documentation
+    /// Read a request from a line written by `DaemonRequest::to_line()`
+    ///
+    /// # Errors
+    /// Returns an error if the line has an unclosed quote
+    /// Returns an error if the line isn't a valid request
+    pub fn parse_line<S: AsRef<str>>(line: S) -> Result<Self, ThemerError> {
+        let args = split_command_template(line.as_ref()).map_err(|e| ThemerError::DaemonRequestError(e.to_string()))?;
+
+        DaemonRequestLine::try_parse_from(args)
+            .map(|request_line| request_line.request)
+            .map_err(|e| ThemerError::DaemonRequestError(e.to_string().trim().to_string()))
+    }
+}
+
+// Quote an argument if it would otherwise be split by `split_command_template()`. Quotes can't be escaped, so the parts
+// between each `'` are single-quoted and joined by a double-quoted `'`, which are read back as a single argument
+fn quote_arg(arg: &str) -> String {
+    if !arg.is_empty() && !arg.contains(|c: char| c.is_whitespace() || c == '\'' || c == '"') {
+        arg.to_string()
+    } else {
+        arg.split('\'')
+            .map(|part| format!("'{part}'"))
+            .collect::<Vec<_>>()
+            .join("\"'\"")
+    }
+}
+
+/// # Documentation
+/// Get the path of the socket which the daemon listens on, in a runtime directory which only the current user can use,
+/// so no other user can listen on it
+///
+/// # Errors
+/// Returns an error if `get_private_runtime_dir()` fails
+pub fn get_socket_path() -> Result<String, ThemerError> {
+    Ok(format!("{}/{SOCKET_FILE_NAME}", get_private_runtime_dir()?))
+}
+
+/// # Documentation
+/// Send a request to the daemon, writing any output it sends back to `out`.
+/// Gives `false` if there is no daemon listening, so the request can be run in-process instead
+///
+/// # Errors
+/// Returns an error if the request could not be sent, or the response could not be read
+/// Returns an error if the daemon could not complete the request, with the error it sent back
+/// Returns an error if `out` could not be written to
+pub fn send_daemon_request<W: Write>(request: &DaemonRequest, mut out: W) -> Result<bool, ThemerError> {
+    // No daemon can be listening in a runtime directory which can't be used, so the request is run in-process instead
+    let Ok(socket_path) = get_socket_path() else {
+        return Ok(false);
+    };
+
+    // The socket is left behind if the daemon didn't exit cleanly, so any failure to connect means it isn't running
+    let Ok(mut stream) = UnixStream::connect(socket_path) else {
+        return Ok(false);
+    };
+
+    writeln!(stream, "{}", request.to_line()).map_err(|e| ThemerError::SocketError(e.to_string()))?;
+
+    let mut error_lines = Vec::new();
+    for line in BufReader::new(stream).lines() {
+        let line = line.map_err(|e| ThemerError::SocketError(e.to_string()))?;
+        let (kind, text) = line.split_once(' ').unwrap_or((line.as_str(), ""));
+
+        match kind {
+            RESPONSE_DATA => writeln!(out, "{text}")?,
+            RESPONSE_ERROR => error_lines.push(text.to_string()),
+            RESPONSE_OK => return Ok(true),
+            RESPONSE_FAILED => return Err(ThemerError::DaemonError(error_lines.join("\n"))),
+            _ => {
+                return Err(ThemerError::SocketError(format!(
+                    "Unexpected response from the daemon: '{line}'"
+                )));
+            }
+        }
+    }
+
+    Err(ThemerError::SocketError(String::from(
+        "Daemon closed the connection before it finished responding",
+    )))
+}
+
+/// # Documentation
+/// Listen for requests on `get_socket_path()`, keeping the config and selected theme loaded between requests.
+/// Each connection is handled on its own thread, so a sound can be stopped while it is playing
+///
+/// # Errors
+/// Returns an error if `get_socket_path()` fails
+/// Returns an error if another daemon is already listening on the socket
+/// Returns an error if the config or the selected theme could not be loaded
+/// Returns an error if the socket could not be created
+/// Returns an error if `out` could not be written to
+pub fn run_daemon<W: Write>(mut out: W) -> Result<(), ThemerError> {
+    let socket_path = get_socket_path()?;
+
+    if Path::new(&socket_path).exists() {
+        if UnixStream::connect(&socket_path).is_ok() {
+            return Err(ThemerError::DaemonRunningError(socket_path));
+        }
+
+        // Remove the socket left behind by a daemon which didn't exit cleanly
+        fs::remove_file(&socket_path).map_err(|e| ThemerError::SocketError(format!("'{socket_path}': {e}")))?;
+    }
+
+    // Load the config and theme before listening, so they aren't read again for each request
+    get_toml_config()?;
+    let theme = get_selected_theme()?;
+
+    // Only the current user can send requests, so the socket is created without permissions for anyone else
+    // SAFETY: `umask()` can't fail, and the previous mask is restored as soon as the socket has been created
+    let previous_umask = unsafe { libc::umask(0o177) };
+    let listener = UnixListener::bind(&socket_path);
+    // SAFETY: As above
+    unsafe { libc::umask(previous_umask) };
+
+    let listener = listener.map_err(|e| ThemerError::SocketError(format!("'{socket_path}': {e}")))?;
+
+    writeln!(out, "Listening on '{socket_path}' with theme '{}'", theme.name)?;
+    out.flush()?;
+
+    for stream in listener.incoming() {
+        match stream {
+            Ok(stream) => {
+                thread::spawn(move || {
+                    if let Err(e) = handle_daemon_connection(stream) {
+                        eprintln!("Error: {e}");
+                    }
+                });
+            }
+            Err(e) => eprintln!("Error: {}", ThemerError::SocketError(e.to_string())),
+        }
+    }
+
+    Ok(())
+}
+
+/// # Errors
+/// Returns an error if a request could not be read, or a response could not be written
+fn handle_daemon_connection(stream: UnixStream) -> Result<(), ThemerError> {
+    let reader = BufReader::new(stream.try_clone().map_err(|e| ThemerError::SocketError(e.to_string()))?);
+    let mut writer = ResponseWriter::new(stream);
+
+    // A connection can send multiple requests, each of which is answered before the next one is read
+    for line in reader.lines() {
+        let line = line.map_err(|e| ThemerError::SocketError(e.to_string()))?;
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let result = DaemonRequest::parse_line(&line).and_then(|request| evaluate_daemon_request(&request, &mut writer));
+        writer.finish(result)?;
+    }
+
+    Ok(())
+}
+
+/// # Errors
+/// Returns an error if the sound could not be resolved or played
+/// Returns an error if the theme could not be listed or selected
+/// Returns an error if `out` could not be written to
+fn evaluate_daemon_request<W: Write>(request: &DaemonRequest, out: W) -> Result<(), ThemerError> {
+    match request {
+        DaemonRequest::Play {
+            sound_names,
+            duration,
+            range,
+            volume,
+            fade_out,
+            parallel,
+            gap,
+            verbose,
+        } => {
+            let overrides = PlaybackOverrides {
+                duration: duration.clone(),
+                volume: *volume,
+                fade_out: fade_out.clone(),
+                range: range.clone().unwrap_or_default(),
+            };
+
+            play_sound_items(sound_names, &overrides, *parallel, gap.as_ref(), *verbose, out)?;
+        }
+        DaemonRequest::Stop => stop_sounds(),
+        DaemonRequest::List => write_theme_sounds(out)?,
+        DaemonRequest::SetTheme { theme_name } => select_theme_by_name(theme_name)?,
+    }
+
+    Ok(())
+}
+
+// Sends each line written to it as a `RESPONSE_DATA` line
+struct ResponseWriter {
+    stream: UnixStream,
+    line: Vec<u8>,
+}
+
+impl ResponseWriter {
+    const fn new(stream: UnixStream) -> Self {
+        Self {
+            stream,
+            line: Vec::new(),
+        }
+    }
+
+    // Send any unfinished line, followed by the result of the request
+    fn finish(&mut self, result: Result<(), ThemerError>) -> Result<(), ThemerError> {
+        self.flush()?;
+
+        let to_socket_error = |e: std::io::Error| ThemerError::SocketError(e.to_string());
+
+        match result {
+            Ok(()) => writeln!(self.stream, "{RESPONSE_OK}").map_err(to_socket_error),
+            Err(e) => {
+                for line in e.to_string().lines() {
+                    writeln!(self.stream, "{RESPONSE_ERROR} {line}").map_err(to_socket_error)?;
+                }
+
+                writeln!(self.stream, "{RESPONSE_FAILED}").map_err(to_socket_error)
+            }
+        }
+    }
+}
+
+impl Write for ResponseWriter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        for &byte in buf {
+            if byte == b'\n' {
+                self.stream.write_all(format!("{RESPONSE_DATA} ").as_bytes())?;
+                self.stream.write_all(&self.line)?;
+                self.stream.write_all(b"\n")?;
+                self.line.clear();
+            } else {
+                self.line.push(byte);
+            }
+        }
+
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        if !self.line.is_empty() {
+            self.write_all(b"\n")?;
+        }
+
+        self.stream.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+
+    fn get_sound_names(request: &DaemonRequest) -> Vec<String> {
+        match request {
+            DaemonRequest::Play { sound_names, .. } => sound_names.iter().map(ToString::to_string).collect(),
+            _ => Vec::new(),
+        }
+    }
+
+    #[test]
+    fn play_request_round_trips() -> Result<(), ThemerError> {
+        let sound_names = [
+            "bell",
+            "with space",
+            "it's \"x\"",
+            "\"quoted\"",
+            "-dash",
+            "--",
+            "message@0.25s",
+        ]
+        .into_iter()
+        .map(|sound_name| SoundItem {
+            sound_name: sound_name.to_string(),
+            duration: None,
+        })
+        .collect::<Vec<_>>();
+        let request = DaemonRequest::Play {
+            sound_names,
+            duration: Some(PlaybackDuration::from_str("250ms")?),
+            range: Some(PlaybackRange::from_str("..-200ms")?),
+            volume: Some(Volume::from_str("-3dB")?),
+            fade_out: None,
+            parallel: true,
+            gap: None,
+            verbose: false,
+        };
+
+        let parsed = DaemonRequest::parse_line(request.to_line())?;
+
+        assert_eq!(get_sound_names(&parsed), get_sound_names(&request));
+        assert_eq!(parsed.to_line(), request.to_line());
+
+        Ok(())
+    }
+
+    #[test]
+    fn set_theme_request_round_trips() -> Result<(), ThemerError> {
+        for theme_name in ["freedesktop", "my theme", "-theme", "it's \"x\""] {
+            let request = DaemonRequest::SetTheme {
+                theme_name: theme_name.to_string(),
+            };
+
+            match DaemonRequest::parse_line(request.to_line())? {
+                DaemonRequest::SetTheme {
+                    theme_name: parsed_theme_name,
+                } => assert_eq!(parsed_theme_name, theme_name),
+                parsed => panic!("Parsed the wrong request: {parsed:?}"),
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,110 @@
+use std::{collections::HashMap, io::Write, thread};
+
+use zbus::{blocking::connection, fdo, interface};
+
+use crate::{
+    canberra::{CanberraEvent, PROP_EVENT_ID},
+    config::get_toml_config,
+    error::ThemerError,
+    sound::{play_playback_steps, stop_sounds},
+    theme::{get_selected_theme, select_theme_by_name},
+};
+
+/// # Documentation
+/// The name which the service owns on the session bus
+pub const DBUS_NAME: &str = "io.github.tmforshaw.SoundThemer";
+/// # Documentation
+/// The path of the object which implements `DBUS_INTERFACE`
+pub const DBUS_PATH: &str = "/io/github/tmforshaw/SoundThemer";
+/// # Documentation
+/// The name of the interface which sounds are played through
+pub const DBUS_INTERFACE: &str = "io.github.tmforshaw.SoundThemer1";
+
+// The object served at `DBUS_PATH`, which resolves events using the config and theme which are loaded when it starts
+struct SoundThemerService;
+
+// The methods are called by zbus, so they must take `&self` and owned arguments
+#[allow(clippy::unused_self, clippy::needless_pass_by_value)]
+#[interface(name = "io.github.tmforshaw.SoundThemer1")]
+impl SoundThemerService {
+    /// # Documentation
+    /// Play the sound for `event_id`, where `properties` are libcanberra properties (e.g: `canberra.volume`).
+    /// Returns once the sound has been found, without waiting for it to finish playing
+    fn play_event(&self, event_id: String, mut properties: HashMap<String, String>) -> fdo::Result<()> {
+        properties.insert(PROP_EVENT_ID.to_string(), event_id);
+
+        play_canberra_event(&properties).map_err(|e| fdo::Error::Failed(e.to_string()))
+    }
+
+    /// # Documentation
+    /// Play a sound using only libcanberra properties, where `event.id` or `media.filename` sets the sound.
+    /// Returns once the sound has been found, without waiting for it to finish playing
+    fn play(&self, properties: HashMap<String, String>) -> fdo::Result<()> {
+        play_canberra_event(&properties).map_err(|e| fdo::Error::Failed(e.to_string()))
+    }
+
+    /// # Documentation
+    /// Stop every sound which the service is playing
+    fn stop(&self) {
+        stop_sounds();
+    }
+
+    /// # Documentation
+    /// Change the theme which events are played from
+    fn set_theme(&self, theme_name: String) -> fdo::Result<()> {
+        select_theme_by_name(theme_name).map_err(|e| fdo::Error::Failed(e.to_string()))
+    }
+
+    /// # Documentation
+    /// The name of the theme which events are played from
+    #[zbus(property)]
+    fn theme(&self) -> fdo::Result<String> {
+        get_selected_theme()
+            .map(|theme| theme.name)
+            .map_err(|e| fdo::Error::Failed(e.to_string()))
+    }
+}
+
+/// # Errors
+/// Returns an error if `CanberraEvent::from_properties()` or `CanberraEvent::resolve()` fails
+fn play_canberra_event(properties: &HashMap<String, String>) -> Result<(), ThemerError> {
+    let event = CanberraEvent::from_properties(properties)?;
+    let steps = event.resolve()?;
+
+    // The sound is played in the background, so the caller doesn't wait for it to finish
+    thread::spawn(move || {
+        if let Err(e) = play_playback_steps(&steps) {
+            eprintln!("Error: Event '{}' could not be played:\t{e}", event.name());
+        }
+    });
+
+    Ok(())
+}
+
+/// # Documentation
+/// Own `DBUS_NAME` on the session bus and play the events which are requested through `DBUS_INTERFACE`, until the
+/// process is stopped
+///
+/// # Errors
+/// Returns an error if the config or the selected theme could not be loaded
+/// Returns an error if the session bus could not be connected to, or `DBUS_NAME` is already owned
+/// Returns an error if `out` could not be written to
+pub fn run_dbus_service<W: Write>(mut out: W) -> Result<(), ThemerError> {
+    // Load the config and theme before the name is owned, so they aren't read again for each event
+    get_toml_config()?;
+    let theme = get_selected_theme()?;
+
+    let _connection = connection::Builder::session()
+        .and_then(|builder| builder.name(DBUS_NAME))
+        .and_then(|builder| builder.serve_at(DBUS_PATH, SoundThemerService))
+        .and_then(connection::Builder::build)
+        .map_err(|e| ThemerError::DbusError(e.to_string()))?;
+
+    writeln!(out, "Serving '{DBUS_INTERFACE}' as '{DBUS_NAME}' with theme '{}'", theme.name)?;
+    out.flush()?;
+
+    // Requests are handled on the connection's own threads
+    loop {
+        thread::park();
+    }
+}
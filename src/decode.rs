@@ -0,0 +1,175 @@
+use std::{fs::File, io::ErrorKind, time::Duration};
+
+use symphonia::{
+    core::{
+        audio::SampleBuffer,
+        codecs::DecoderOptions,
+        errors::Error as SymphoniaError,
+        formats::{FormatOptions, FormatReader},
+        io::{MediaSourceStream, MediaSourceStreamOptions},
+        meta::MetadataOptions,
+        probe::Hint,
+    },
+    default::{get_codecs, get_probe},
+};
+
+use crate::error::ThemerError;
+
+/// # Documentation
+/// The sample rate and channel count of some PCM samples
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SoundSpec {
+    pub sample_rate: u32,
+    pub channels: u16,
+}
+
+/// # Documentation
+/// A fully decoded sound, stored as interleaved `f32` samples
+#[derive(Debug, Clone, PartialEq)]
+pub struct DecodedSound {
+    pub samples: Vec<f32>,
+    pub spec: SoundSpec,
+}
+
+impl DecodedSound {
+    /// # Documentation
+    /// Get the number of frames (One sample for each channel) in the sound
+    #[must_use]
+    pub fn frame_count(&self) -> usize {
+        self.samples.len() / usize::from(self.spec.channels.max(1))
+    }
+
+    /// # Documentation
+    /// Get the duration of the sound
+    #[must_use]
+    #[allow(clippy::cast_precision_loss)]
+    pub fn duration(&self) -> Duration {
+        Duration::from_secs_f64(self.frame_count() as f64 / f64::from(self.spec.sample_rate.max(1)))
+    }
+
+    /// # Documentation
+    /// Get the number of frames which are played during `duration`, rounded to the nearest frame
+    #[must_use]
+    #[allow(clippy::cast_sign_loss)]
+    pub fn frames_in_duration(&self, duration: Duration) -> usize {
+        (duration.as_secs_f64() * f64::from(self.spec.sample_rate)).round() as usize
+    }
+
+    /// # Documentation
+    /// Remove every frame before `duration`, so the sound starts at an exact sample boundary
+    pub fn skip(&mut self, duration: Duration) {
+        let frames = self.frames_in_duration(duration).min(self.frame_count());
+
+        self.samples.drain(..frames * usize::from(self.spec.channels));
+    }
+
+    /// # Documentation
+    /// Remove every frame after `duration`, cutting the sound at an exact sample boundary
+    pub fn truncate(&mut self, duration: Duration) {
+        let frames = self.frames_in_duration(duration).min(self.frame_count());
+
+        self.samples.truncate(frames * usize::from(self.spec.channels));
+    }
+
+    /// # Documentation
+    /// Linearly fade the last `duration` of the sound out to silence
+    #[allow(clippy::cast_precision_loss)]
+    pub fn fade_out(&mut self, duration: Duration) {
+        let channels = usize::from(self.spec.channels.max(1));
+        let frame_count = self.frame_count();
+        let fade_frames = self.frames_in_duration(duration).min(frame_count);
+
+        for (i, frame) in self.samples.chunks_mut(channels).skip(frame_count - fade_frames).enumerate() {
+            // The gain reaches zero on the final frame
+            let gain = 1.0 - (i + 1) as f32 / fade_frames as f32;
+            for sample in frame {
+                *sample *= gain;
+            }
+        }
+    }
+
+    /// # Documentation
+    /// Multiply every sample by a linear `volume`
+    pub fn scale(&mut self, volume: f32) {
+        // Skip the multiplication if it wouldn't change anything
+        if (volume - 1.0).abs() > f32::EPSILON {
+            self.samples.iter_mut().for_each(|sample| *sample *= volume);
+        }
+    }
+}
+
+/// # Errors
+/// Returns an error if the sound file could not be opened as a `File`
+/// Returns an error if the file probe could not be created for the sound file
+pub fn probe_sound_file<S: AsRef<str>>(sound_file_str: S) -> Result<Box<dyn FormatReader>, ThemerError> {
+    let sound_file = File::open(sound_file_str.as_ref()).map_err(|e| ThemerError::FileReadWriteError(e.to_string()))?;
+
+    // Get the MediaSourceStream for this file
+    let mss = MediaSourceStream::new(Box::new(sound_file), MediaSourceStreamOptions::default());
+
+    // Probe the file to get its format
+    let probe = get_probe()
+        .format(&Hint::default(), mss, &FormatOptions::default(), &MetadataOptions::default())
+        .map_err(|e| ThemerError::SoundDecoderError(e.to_string()))?;
+
+    Ok(probe.format)
+}
+
+/// # Errors
+/// Returns an error if `probe_sound_file()` fails
+/// Returns an error if the default track couldn't be acquired
+/// Returns an error if a decoder could not be created for the track's codec
+/// Returns an error if a packet could not be read or decoded
+pub fn decode_sound_file<S: AsRef<str>>(sound_file_str: S) -> Result<DecodedSound, ThemerError> {
+    let mut format = probe_sound_file(sound_file_str.as_ref())?;
+
+    // Get the default track from the format, then create a decoder for it
+    let track = format
+        .default_track()
+        .ok_or_else(|| ThemerError::SoundDecoderError(format!("Track could not be found for '{}'", sound_file_str.as_ref())))?;
+    let track_id = track.id;
+    let mut decoder = get_codecs()
+        .make(&track.codec_params, &DecoderOptions::default())
+        .map_err(|e| ThemerError::SoundDecoderError(e.to_string()))?;
+
+    let mut samples = Vec::new();
+    let mut spec = None;
+
+    loop {
+        let packet = match format.next_packet() {
+            Ok(packet) => packet,
+            // The end of the stream has been reached
+            Err(SymphoniaError::IoError(e)) if e.kind() == ErrorKind::UnexpectedEof => break,
+            Err(e) => return Err(ThemerError::SoundDecoderError(e.to_string())),
+        };
+
+        // Skip packets which belong to other tracks
+        if packet.track_id() != track_id {
+            continue;
+        }
+
+        match decoder.decode(&packet) {
+            Ok(audio_buffer) => {
+                let signal_spec = *audio_buffer.spec();
+
+                // Convert the decoded samples into interleaved f32 samples
+                let mut buffer = SampleBuffer::<f32>::new(audio_buffer.capacity() as u64, signal_spec);
+                buffer.copy_interleaved_ref(audio_buffer);
+                samples.extend_from_slice(buffer.samples());
+
+                spec.get_or_insert_with(|| SoundSpec {
+                    sample_rate: signal_spec.rate,
+                    channels: signal_spec.channels.count() as u16,
+                });
+            }
+            // Corrupted packets can be skipped
+            Err(SymphoniaError::DecodeError(_)) => {}
+            Err(e) => return Err(ThemerError::SoundDecoderError(e.to_string())),
+        }
+    }
+
+    let spec = spec
+        .ok_or_else(|| ThemerError::SoundDecoderError(format!("No audio could be decoded from '{}'", sound_file_str.as_ref())))?;
+
+    Ok(DecodedSound { samples, spec })
+}
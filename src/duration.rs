@@ -1,6 +1,9 @@
-use std::{str::FromStr, time::Duration};
+use std::{fmt, str::FromStr, time::Duration};
 
-use crate::{error::ThemerError, sound::get_sound_duration_from_name};
+use crate::{
+    error::ThemerError,
+    sound::{get_sound_duration_from_name, get_sound_duration_from_path},
+};
 
 #[derive(Debug, Clone)]
 pub enum PlaybackDuration {
@@ -34,23 +37,202 @@ impl FromStr for PlaybackDuration {
         } else if let Some(millisecs) = s.strip_suffix("ms") {
             // Parse the duration into an f64
             let value = millisecs.parse::<f64>().map_err(|_| ThemerError::PlaybackFromStrError {
-                from: s,
+                from: s.clone(),
                 e: String::from("Milliseconds value must be a numerical value followed by 'ms'"),
             })?;
 
-            Ok(Self::Time(Duration::from_secs_f64(value / 1000.)))
+            Ok(Self::Time(Duration::try_from_secs_f64(value / 1000.).map_err(|_| {
+                ThemerError::PlaybackFromStrError {
+                    from: s,
+                    e: String::from("Milliseconds value must not be negative"),
+                }
+            })?))
         } else {
             // Allow with or without 's' suffix
             let secs = s.strip_suffix("s").unwrap_or(s.as_str());
 
             // Parse the duration into an f64
             let value = secs.parse::<f64>().map_err(|_| ThemerError::PlaybackFromStrError {
-                from: s,
+                from: s.clone(),
                 e: String::from("Seconds value must be a numerical value (optionally followed by 's')"),
             })?;
 
-            Ok(Self::Time(Duration::from_secs_f64(value)))
+            Ok(Self::Time(Duration::try_from_secs_f64(value).map_err(|_| {
+                ThemerError::PlaybackFromStrError {
+                    from: s,
+                    e: String::from("Seconds value must not be negative"),
+                }
+            })?))
+        }
+    }
+}
+
+impl fmt::Display for PlaybackDuration {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Written in the same format as it is read, so it can be passed to `from_str()` again
+        match self {
+            Self::Time(duration) => write!(f, "{}s", duration.as_secs_f64()),
+            Self::Percent(percent) => write!(f, "{}%", percent * 100.0),
+        }
+    }
+}
+
+impl PlaybackDuration {
+    /// # Errors
+    /// Returns an error if `PlaybackDuration::Time` has duration longer than `sound_duration`
+    pub fn to_duration(&self, sound_duration: Duration) -> Result<Duration, ThemerError> {
+        match self {
+            Self::Time(duration) => {
+                if &sound_duration >= duration {
+                    Ok(*duration)
+                } else {
+                    Err(ThemerError::DurationTooLongError(
+                        sound_duration.as_secs_f32(),
+                        duration.as_secs_f32(),
+                    ))
+                }
+            }
+            Self::Percent(percent) => Ok(sound_duration.mul_f32(*percent)),
+        }
+    }
+
+    /// # Documentation
+    /// Get the length of a gap between sounds, which must be a time since it isn't part of a sound
+    ///
+    /// # Errors
+    /// Returns an error if the duration is a percentage
+    pub fn to_gap(&self) -> Result<Duration, ThemerError> {
+        match self {
+            Self::Time(duration) => Ok(*duration),
+            Self::Percent(_) => Err(ThemerError::PlaybackFromStrError {
+                from: self.to_string(),
+                e: String::from("A gap must be a time (e.g: 100ms), since it isn't part of a sound"),
+            }),
+        }
+    }
+}
+
+/// # Documentation
+/// A position in a sound, measured from its start, or from its end if it is negative (e.g: 250ms, 10%, -200ms)
+#[derive(Debug, Clone)]
+pub struct PlaybackOffset {
+    pub from_end: bool,
+    pub duration: PlaybackDuration,
+}
+
+impl FromStr for PlaybackOffset {
+    type Err = ThemerError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+
+        s.strip_prefix('-').map_or_else(
+            || {
+                Ok(Self {
+                    from_end: false,
+                    duration: PlaybackDuration::from_str(s)?,
+                })
+            },
+            |from_end| {
+                Ok(Self {
+                    from_end: true,
+                    duration: PlaybackDuration::from_str(from_end)?,
+                })
+            },
+        )
+    }
+}
+
+impl fmt::Display for PlaybackOffset {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.from_end {
+            write!(f, "-")?;
+        }
+
+        write!(f, "{}", self.duration)
+    }
+}
+
+impl PlaybackOffset {
+    /// # Errors
+    /// Returns an error if `PlaybackDuration::to_duration()` fails
+    pub fn to_duration(&self, sound_duration: Duration) -> Result<Duration, ThemerError> {
+        let duration = self.duration.to_duration(sound_duration)?;
+
+        Ok(if self.from_end {
+            sound_duration.saturating_sub(duration)
+        } else {
+            duration
+        })
+    }
+}
+
+/// # Documentation
+/// A section of a sound, between two optional `PlaybackOffset`s (e.g: 250ms..1s, 10%..90%, ..-200ms)
+#[derive(Debug, Clone, Default)]
+pub struct PlaybackRange {
+    pub start: Option<PlaybackOffset>,
+    pub end: Option<PlaybackOffset>,
+}
+
+impl FromStr for PlaybackRange {
+    type Err = ThemerError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (start, end) = s.trim().split_once("..").ok_or_else(|| ThemerError::PlaybackFromStrError {
+            from: s.to_string(),
+            e: String::from("Range must be two optional positions separated by '..' (e.g: 250ms..1s, 10%..90%, ..-200ms)"),
+        })?;
+
+        // Either side of the range can be left empty
+        let parse_offset = |offset: &str| {
+            let offset = offset.trim();
+            if offset.is_empty() {
+                Ok(None)
+            } else {
+                PlaybackOffset::from_str(offset).map(Some)
+            }
+        };
+
+        Ok(Self {
+            start: parse_offset(start)?,
+            end: parse_offset(end)?,
+        })
+    }
+}
+
+impl fmt::Display for PlaybackRange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(start) = &self.start {
+            write!(f, "{start}")?;
         }
+
+        write!(f, "..")?;
+
+        if let Some(end) = &self.end {
+            write!(f, "{end}")?;
+        }
+
+        Ok(())
+    }
+}
+
+impl PlaybackRange {
+    /// # Documentation
+    /// Use the start and end of `other` where this range doesn't set them
+    #[must_use]
+    pub fn or(self, other: Self) -> Self {
+        Self {
+            start: self.start.or(other.start),
+            end: self.end.or(other.end),
+        }
+    }
+
+    /// # Documentation
+    /// Whether the range covers the whole sound
+    #[must_use]
+    pub const fn is_full(&self) -> bool {
+        self.start.is_none() && self.end.is_none()
     }
 }
 
@@ -64,17 +246,156 @@ pub fn playback_duration_to_duration<S: AsRef<str>>(
     // Map the PlaybackDuration to a true Duration
     let sound_duration = get_sound_duration_from_name(sound_name)?;
 
-    match playback_duration {
-        PlaybackDuration::Time(duration) => {
-            if &sound_duration >= duration {
-                Ok(*duration)
-            } else {
-                Err(ThemerError::DurationTooLongError(
+    playback_duration.to_duration(sound_duration)
+}
+
+/// # Documentation
+/// Map the range, and the duration which is played from its start if it has no end, to the true `Duration` which
+/// playback of the sound file starts at and how long the sound is played for
+///
+/// # Errors
+/// Returns an error if sound duration cant be gotten from `get_sound_duration_from_path()`
+/// Returns an error if any position in the range, or the end of the duration, is past the end of the sound
+/// Returns an error if the range starts after it ends
+pub fn playback_range_to_durations<S: AsRef<str>>(
+    playback_range: &PlaybackRange,
+    playback_duration: Option<&PlaybackDuration>,
+    sound_path_str: S,
+) -> Result<(Option<Duration>, Option<Duration>), ThemerError> {
+    // The sound file doesn't need to be read if nothing is set
+    if playback_range.is_full() && playback_duration.is_none() {
+        return Ok((None, None));
+    }
+
+    let sound_duration = get_sound_duration_from_path(sound_path_str)?;
+
+    // Only the duration needs to be mapped
+    if playback_range.is_full() {
+        return Ok((
+            None,
+            playback_duration
+                .map(|playback_duration| playback_duration.to_duration(sound_duration))
+                .transpose()?,
+        ));
+    }
+
+    let start = playback_range
+        .start
+        .as_ref()
+        .map(|start| start.to_duration(sound_duration))
+        .transpose()?
+        .unwrap_or_default();
+
+    let end = match (&playback_range.end, playback_duration) {
+        (Some(end), _) => Some(end.to_duration(sound_duration)?),
+        (None, Some(playback_duration)) => {
+            let end = start + playback_duration.to_duration(sound_duration)?;
+
+            if end > sound_duration {
+                return Err(ThemerError::DurationTooLongError(
                     sound_duration.as_secs_f32(),
-                    duration.as_secs_f32(),
-                ))
+                    end.as_secs_f32(),
+                ));
             }
+
+            Some(end)
         }
-        PlaybackDuration::Percent(percent) => Ok(sound_duration.mul_f32(*percent)),
+        (None, None) => None,
+    };
+
+    match end {
+        Some(end) if end < start => Err(ThemerError::PlaybackRangeError(start.as_secs_f32(), end.as_secs_f32())),
+        _ => Ok((Some(start), end.map(|end| end.saturating_sub(start)))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SOUND_DURATION: Duration = Duration::from_secs(2);
+
+    #[test]
+    fn offsets_are_measured_from_either_end() -> Result<(), ThemerError> {
+        let offset = PlaybackOffset::from_str("250ms")?;
+        assert!(!offset.from_end);
+        assert_eq!(offset.to_duration(SOUND_DURATION)?, Duration::from_millis(250));
+
+        let offset = PlaybackOffset::from_str(" -200ms ")?;
+        assert!(offset.from_end);
+        assert_eq!(offset.to_duration(SOUND_DURATION)?, Duration::from_millis(1800));
+
+        let offset = PlaybackOffset::from_str("-25%")?;
+        assert!(offset.from_end);
+        assert_eq!(offset.to_duration(SOUND_DURATION)?, Duration::from_millis(1500));
+
+        // Offsets past the end of the sound can't be used
+        assert!(PlaybackOffset::from_str("3s")?.to_duration(SOUND_DURATION).is_err());
+        assert!(PlaybackOffset::from_str("--1s").is_err());
+        assert!(PlaybackOffset::from_str("-").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn ranges_can_leave_either_side_empty() -> Result<(), ThemerError> {
+        let range = PlaybackRange::from_str("-200ms..")?;
+        assert!(range.start.as_ref().is_some_and(|start| start.from_end));
+        assert!(range.end.is_none());
+
+        let range = PlaybackRange::from_str("..-200ms")?;
+        assert!(range.start.is_none());
+        assert!(range.end.as_ref().is_some_and(|end| end.from_end));
+
+        let range = PlaybackRange::from_str(" 20% .. 80% ")?;
+        assert!(matches!(
+            range.start,
+            Some(PlaybackOffset {
+                from_end: false,
+                duration: PlaybackDuration::Percent(_)
+            })
+        ));
+        assert!(!range.is_full());
+
+        assert!(PlaybackRange::from_str("..")?.is_full());
+        assert!(PlaybackRange::from_str("250ms").is_err());
+        assert!(PlaybackRange::from_str("1s..loud").is_err());
+        assert!(PlaybackRange::from_str("150%..").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn ranges_round_trip_through_display() -> Result<(), ThemerError> {
+        for (range_str, expected) in [
+            ("250ms..1s", "0.25s..1s"),
+            ("20%..80%", "20%..80%"),
+            ("-200ms..", "-0.2s.."),
+            ("..-10%", "..-10%"),
+            ("1.5..", "1.5s.."),
+            ("..", ".."),
+        ] {
+            let range = PlaybackRange::from_str(range_str)?;
+            assert_eq!(range.to_string(), expected);
+
+            // Reading the written range gives the same range again
+            assert_eq!(PlaybackRange::from_str(&range.to_string())?.to_string(), expected);
+        }
+
+        assert_eq!(PlaybackOffset::from_str("-200ms")?.to_string(), "-0.2s");
+        assert_eq!(PlaybackOffset::from_str("12.5%")?.to_string(), "12.5%");
+
+        Ok(())
+    }
+
+    #[test]
+    fn missing_sides_are_taken_from_the_other_range() -> Result<(), ThemerError> {
+        let range = PlaybackRange::from_str("100ms..")?.or(PlaybackRange::from_str("50ms..1s")?);
+        assert_eq!(range.to_string(), "0.1s..1s");
+
+        let range = PlaybackRange::default().or(PlaybackRange::from_str("..-200ms")?);
+        assert_eq!(range.to_string(), "..-0.2s");
+
+        Ok(())
     }
 }
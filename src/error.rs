@@ -1,3 +1,5 @@
+use std::ops::Range;
+
 use thiserror::Error;
 
 #[derive(Error, Debug)]
@@ -5,6 +7,25 @@ pub enum ThemerError {
     #[error("TOML could not be read to string:\t\"{0}\"")]
     TomlReadError(#[from] toml::de::Error),
 
+    #[error("Config '{path}' could not be parsed:\n{e}")]
+    ConfigParseError {
+        path: String,
+        span: Option<Range<usize>>,
+        e: String,
+    },
+
+    #[error("Config has {count} problem(s):\t'{path}'")]
+    ConfigInvalidError { path: String, count: usize },
+
+    #[error("Config already exists at '{0}', use '--force' to overwrite it")]
+    ConfigExistsError(String),
+
+    #[error("Config override '{key}' could not be applied:\t{e}")]
+    ConfigOverrideError { key: String, e: String },
+
+    #[error("TOML could not be written:\t\"{0}\"")]
+    TomlWriteError(#[from] toml::ser::Error),
+
     #[error("File could not be read/written to:\t\"{0}\"")]
     FileReadWriteError(String),
 
@@ -23,6 +44,12 @@ pub enum ThemerError {
     #[error("Command '{name}' with args '{args:?}' could not run:\t\"{e}\"")]
     CommandError { name: String, args: Vec<String>, e: String },
 
+    #[error("No sound player could be found in $PATH, tried:\t{0}")]
+    PlayerNotFoundError(String),
+
+    #[error("Invalid player configuration:\t\"{0}\"")]
+    PlayerConfigError(String),
+
     #[error("Bytes could not be converted to String:\t\"{0}\"")]
     StringFromUtf8Error(#[from] std::string::FromUtf8Error),
 
@@ -35,18 +62,67 @@ pub enum ThemerError {
     #[error("Themes HashMap didn't contain entry '{0}'")]
     HashMapEntryError(String),
 
+    #[error("Theme '{name}' could not be extended:\t{e}")]
+    ThemeExtendsError { name: String, e: String },
+
     #[error("Could not create a PlaybackDuration from '{from}':\t{e}")]
     PlaybackFromStrError { from: String, e: String },
 
+    #[error("Could not read a sound from '{from}':\t{e}")]
+    SoundItemFromStrError { from: String, e: String },
+
+    #[error("Could not create a Volume from '{from}':\t{e}")]
+    VolumeFromStrError { from: String, e: String },
+
     #[error("Could not create a file decoder:\t\"{0}\"")]
     SoundDecoderError(String),
 
+    #[error("Audio could not be written to the output:\t\"{0}\"")]
+    AudioSinkError(String),
+
     #[error("Provided duration was longer than the sound: Expected <= {0:.5}s, Found {1:.5}s")]
     DurationTooLongError(f32, f32),
 
+    #[error("Provided range starts after it ends: Starts at {0:.5}s, Ends at {1:.5}s")]
+    PlaybackRangeError(f32, f32),
+
+    #[error("Mapping alias '{key}' could not be resolved:\t{e}")]
+    MappingAliasError { key: String, e: String },
+
+    #[error("Could not pick a sound from the mapping for '{key}':\t{e}")]
+    MappingPickError { key: String, e: String },
+
+    #[error("Could not parse index.theme at line {line}:\t{e}")]
+    IndexThemeParseError { line: usize, e: String },
+
     #[error("No themes found in config:\t\"{0}\"")]
     EmptyThemesError(String),
 
+    #[error("Daemon socket could not be used:\t\"{0}\"")]
+    SocketError(String),
+
+    #[error("Runtime directory '{path}' could not be used:\t{e}")]
+    RuntimeDirError { path: String, e: String },
+
+    #[error("A daemon is already listening on '{0}'")]
+    DaemonRunningError(String),
+
+    #[error("No daemon is listening on '{0}', start one with 'sound_themer daemon'")]
+    DaemonNotRunningError(String),
+
+    #[error("Daemon request could not be read:\t{0}")]
+    DaemonRequestError(String),
+
+    // Errors from the daemon are shown as they would be if the request was run in-process
+    #[error("{0}")]
+    DaemonError(String),
+
+    #[error("D-Bus service could not be started:\t\"{0}\"")]
+    DbusError(String),
+
+    #[error("Canberra property '{key}' is invalid:\t{e}")]
+    CanberraPropertyError { key: String, e: String },
+
     #[error("I/O Error:\t\"{0}\"")]
     IoError(#[from] std::io::Error),
 }
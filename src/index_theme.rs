@@ -0,0 +1,234 @@
+use std::{collections::HashMap, fs, path::Path, str::FromStr};
+
+use crate::error::ThemerError;
+
+pub const INDEX_THEME_FILE_NAME: &str = "index.theme";
+pub const DEFAULT_OUTPUT_PROFILE: &str = "stereo";
+
+const SOUND_THEME_GROUP: &str = "Sound Theme";
+
+/// # Documentation
+/// A directory listed in the `Directories=` key of an `index.theme` file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IndexDirectory {
+    pub name: String,
+    pub output_profile: String,
+}
+
+/// # Documentation
+/// The contents of a theme's `index.theme` file, as described by the freedesktop Sound Theme Specification
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IndexTheme {
+    pub name: String,
+    pub inherits: Vec<String>,
+    pub directories: Vec<IndexDirectory>,
+}
+
+impl FromStr for IndexTheme {
+    type Err = ThemerError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut groups: HashMap<String, HashMap<String, String>> = HashMap::new();
+        let mut current_group = None;
+
+        for (i, line) in s.lines().enumerate() {
+            let line = line.trim();
+
+            // Skip empty lines and comments
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            // Start of a new group
+            if let Some(group) = line.strip_prefix('[').and_then(|line| line.strip_suffix(']')) {
+                groups.entry(group.to_string()).or_default();
+                current_group = Some(group.to_string());
+
+                continue;
+            }
+
+            let (key, value) = line.split_once('=').ok_or_else(|| ThemerError::IndexThemeParseError {
+                line: i + 1,
+                e: format!("Expected 'Key=Value', found '{line}'"),
+            })?;
+
+            let group = current_group.as_ref().ok_or_else(|| ThemerError::IndexThemeParseError {
+                line: i + 1,
+                e: String::from("Key found before any group header"),
+            })?;
+
+            // Localised keys (e.g: 'Name[de]') are not needed, so only the first occurrence of the plain key is kept
+            groups
+                .entry(group.clone())
+                .or_default()
+                .entry(key.trim().to_string())
+                .or_insert_with(|| value.trim().to_string());
+        }
+
+        let theme_group = groups
+            .get(SOUND_THEME_GROUP)
+            .ok_or_else(|| ThemerError::IndexThemeParseError {
+                line: 0,
+                e: format!("Missing '[{SOUND_THEME_GROUP}]' group"),
+            })?;
+
+        let directories = theme_group
+            .get("Directories")
+            .map(split_list)
+            .unwrap_or_default()
+            .into_iter()
+            .map(|name| IndexDirectory {
+                // Each directory has its own group which can set the OutputProfile (Defaults to stereo)
+                output_profile: groups
+                    .get(&name)
+                    .and_then(|group| group.get("OutputProfile"))
+                    .cloned()
+                    .unwrap_or_else(|| String::from(DEFAULT_OUTPUT_PROFILE)),
+                name,
+            })
+            .collect();
+
+        Ok(Self {
+            name: theme_group.get("Name").cloned().unwrap_or_default(),
+            inherits: theme_group.get("Inherits").map(split_list).unwrap_or_default(),
+            directories,
+        })
+    }
+}
+
+impl IndexTheme {
+    /// # Errors
+    /// Returns an error if the file at `path` could not be read
+    /// Returns an error if the file contents could not be parsed via `IndexTheme::from_str()`
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self, ThemerError> {
+        let contents = fs::read_to_string(path.as_ref())
+            .map_err(|e| ThemerError::FileReadWriteError(format!("{}: {e}", path.as_ref().display())))?;
+
+        Self::from_str(contents.as_str())
+    }
+
+    /// # Documentation
+    /// Get the names of the directories which use `output_profile`, falling back to the stereo profile if there are none
+    #[must_use]
+    pub fn get_directories_for_profile<S: AsRef<str>>(&self, output_profile: S) -> Vec<String> {
+        let get_directories = |profile: &str| {
+            self.directories
+                .iter()
+                .filter(|dir| dir.output_profile == profile)
+                .map(|dir| dir.name.clone())
+                .collect::<Vec<_>>()
+        };
+
+        let directories = get_directories(output_profile.as_ref());
+
+        if directories.is_empty() {
+            get_directories(DEFAULT_OUTPUT_PROFILE)
+        } else {
+            directories
+        }
+    }
+}
+
+/// # Documentation
+/// Split a comma-separated `index.theme` list, removing any empty entries
+fn split_list<S: AsRef<str>>(list: S) -> Vec<String> {
+    list.as_ref()
+        .split(',')
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+        .map(ToString::to_string)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const INDEX_THEME: &str = "
+# A theme which adds surround sounds
+[Sound Theme]
+Name=Example
+Name[de]=Beispiel
+Inherits= freedesktop , ,other,
+Directories=stereo,5.1,extra
+
+[stereo]
+OutputProfile=stereo
+
+[5.1]
+OutputProfile = 5.1
+";
+
+    #[test]
+    fn index_theme_is_parsed() -> Result<(), ThemerError> {
+        let index_theme = IndexTheme::from_str(INDEX_THEME)?;
+
+        assert_eq!(index_theme.name, "Example");
+        // Empty entries are removed from lists
+        assert_eq!(index_theme.inherits, vec![String::from("freedesktop"), String::from("other")]);
+        assert_eq!(
+            index_theme.directories,
+            vec![
+                IndexDirectory {
+                    name: String::from("stereo"),
+                    output_profile: String::from("stereo"),
+                },
+                IndexDirectory {
+                    name: String::from("5.1"),
+                    output_profile: String::from("5.1"),
+                },
+                // A directory without its own group uses the stereo profile
+                IndexDirectory {
+                    name: String::from("extra"),
+                    output_profile: String::from(DEFAULT_OUTPUT_PROFILE),
+                },
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn directories_fall_back_to_the_stereo_profile() -> Result<(), ThemerError> {
+        let index_theme = IndexTheme::from_str(INDEX_THEME)?;
+
+        assert_eq!(index_theme.get_directories_for_profile("5.1"), vec![String::from("5.1")]);
+        assert_eq!(
+            index_theme.get_directories_for_profile("stereo"),
+            vec![String::from("stereo"), String::from("extra")]
+        );
+        assert_eq!(
+            index_theme.get_directories_for_profile("7.1"),
+            vec![String::from("stereo"), String::from("extra")]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn missing_keys_are_empty() -> Result<(), ThemerError> {
+        let index_theme = IndexTheme::from_str("[Sound Theme]\nComment=Nothing else is set")?;
+
+        assert_eq!(index_theme.name, "");
+        assert!(index_theme.inherits.is_empty());
+        assert!(index_theme.directories.is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn invalid_index_themes_are_errors() {
+        assert!(matches!(
+            IndexTheme::from_str("[Icon Theme]\nName=Icons"),
+            Err(ThemerError::IndexThemeParseError { line: 0, .. })
+        ));
+        assert!(matches!(
+            IndexTheme::from_str("Name=Example\n[Sound Theme]"),
+            Err(ThemerError::IndexThemeParseError { line: 1, .. })
+        ));
+        assert!(matches!(
+            IndexTheme::from_str("[Sound Theme]\n\nName Example"),
+            Err(ThemerError::IndexThemeParseError { line: 3, .. })
+        ));
+    }
+}
@@ -0,0 +1,523 @@
+use std::{collections::BTreeMap, fmt, str::FromStr};
+
+use toml::{Table, Value};
+
+use crate::error::ThemerError;
+
+/// # Documentation
+/// How the text of an environment variable is converted to a config value
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EnvValueKind {
+    /// The text is used as it is
+    String,
+    /// The text is read as a TOML value (e.g: `0.8`)
+    Value,
+    /// The text is split into a list of paths, separated by `:` (The same as `$PATH`)
+    PathList,
+}
+
+/// # Documentation
+/// The environment variables which override config values, paired with the dotted path of the value they set
+pub const ENV_OVERRIDES: &[(&str, &str, EnvValueKind)] = &[
+    ("SOUND_THEMER_THEME", "theme_name", EnvValueKind::String),
+    ("SOUND_THEMER_VOLUME", "volume", EnvValueKind::Value),
+    ("SOUND_THEMER_FADE_OUT", "fade_out", EnvValueKind::String),
+    ("SOUND_THEMER_PLAYER", "player.backend", EnvValueKind::String),
+    ("SOUND_THEMER_PLAYER_COMMAND", "player.command", EnvValueKind::String),
+    ("SOUND_THEMER_SEARCH_PATHS", "search_paths", EnvValueKind::PathList),
+];
+
+/// # Documentation
+/// Get the overrides set by each of the `ENV_OVERRIDES` environment variables, where empty variables are ignored
+#[must_use]
+pub fn get_env_config_overrides() -> Vec<ConfigOverride> {
+    ENV_OVERRIDES
+        .iter()
+        .filter_map(|&(var, path, kind)| {
+            let value_str = std::env::var(var).ok().filter(|value_str| !value_str.is_empty())?;
+            let origin = ConfigOrigin::Environment(var.to_string());
+
+            Some(match kind {
+                EnvValueKind::String => ConfigOverride::from_string(path, value_str, origin),
+                EnvValueKind::Value => ConfigOverride::parse(path, value_str, origin),
+                EnvValueKind::PathList => ConfigOverride {
+                    path: path.to_string(),
+                    value: Value::Array(
+                        value_str
+                            .split(':')
+                            .filter(|value| !value.is_empty())
+                            .map(|value| Value::String(value.to_string()))
+                            .collect(),
+                    ),
+                    origin,
+                },
+            })
+        })
+        .collect()
+}
+
+/// # Documentation
+/// The layer which a config value was set by
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConfigOrigin {
+    /// The value wasn't set by any layer, so the default value of the field is used
+    Default,
+    /// The built-in config, compiled from `default/config.toml`
+    BuiltIn,
+    /// A config file, such as `/etc/sound_themer/config.toml`
+    File(String),
+    /// A `SOUND_THEMER_*` environment variable
+    Environment(String),
+    /// A CLI flag, such as `--set`
+    Cli(String),
+}
+
+impl fmt::Display for ConfigOrigin {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Default => write!(f, "default"),
+            Self::BuiltIn => write!(f, "built-in"),
+            Self::File(path) => write!(f, "file '{path}'"),
+            Self::Environment(var) => write!(f, "env ${var}"),
+            Self::Cli(flag) => write!(f, "{flag}"),
+        }
+    }
+}
+
+/// # Documentation
+/// The origin of every value set by the config layers, stored using their dotted paths (e.g: `player.backend`)
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ConfigOrigins(BTreeMap<String, ConfigOrigin>);
+
+impl ConfigOrigins {
+    /// # Documentation
+    /// Get the layer which set the value at `path`, or which set any of the tables or arrays which contain it
+    #[must_use]
+    pub fn get<S: AsRef<str>>(&self, path: S) -> ConfigOrigin {
+        let mut path = path.as_ref();
+
+        loop {
+            if let Some(origin) = self.0.get(path) {
+                return origin.clone();
+            }
+
+            // Move up to the table or array which contains this value
+            match path.rfind(['.', '[']) {
+                Some(i) => path = &path[..i],
+                None => return ConfigOrigin::Default,
+            }
+        }
+    }
+
+    /// # Documentation
+    /// Set the origin of the value at `path`, replacing the origins of any values inside of it
+    pub fn set<S: AsRef<str>>(&mut self, path: S, origin: ConfigOrigin) {
+        let path = path.as_ref();
+
+        self.0
+            .retain(|other_path, _| !(other_path.starts_with(path) && other_path[path.len()..].starts_with(['.', '['])));
+        self.0.insert(path.to_string(), origin);
+    }
+}
+
+/// # Documentation
+/// A single config value which is set on top of the config files, using a dotted path (e.g: `player.backend=paplay`)
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConfigOverride {
+    pub path: String,
+    pub value: Value,
+    pub origin: ConfigOrigin,
+}
+
+impl ConfigOverride {
+    /// # Documentation
+    /// Create an override which sets `path` to a string, without reading it as a TOML value
+    #[must_use]
+    pub fn from_string<P: AsRef<str>, S: AsRef<str>>(path: P, value: S, origin: ConfigOrigin) -> Self {
+        Self {
+            path: path.as_ref().to_string(),
+            value: Value::String(value.as_ref().to_string()),
+            origin,
+        }
+    }
+
+    /// # Documentation
+    /// Create an override which sets `path` to `value_str` read as a TOML value (e.g: `0.8`, `["a", "b"]`, or
+    /// `{name = "bell"}`), where anything which isn't valid TOML is used as a plain string
+    #[must_use]
+    pub fn parse<P: AsRef<str>, S: AsRef<str>>(path: P, value_str: S, origin: ConfigOrigin) -> Self {
+        let value = Some(value_str.as_ref().trim())
+            .filter(|value_str| !value_str.contains('\n'))
+            .and_then(|value_str| toml::from_str::<Table>(&format!("value = {value_str}")).ok())
+            .and_then(|mut table| table.remove("value"))
+            .unwrap_or_else(|| Value::String(value_str.as_ref().to_string()));
+
+        Self {
+            path: path.as_ref().to_string(),
+            value,
+            origin,
+        }
+    }
+}
+
+impl FromStr for ConfigOverride {
+    type Err = ThemerError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (path, value_str) = s.split_once('=').ok_or_else(|| ThemerError::ConfigOverrideError {
+            key: s.to_string(),
+            e: String::from("Overrides must be written as `key=value` (e.g: player.backend=paplay)"),
+        })?;
+
+        let path = path.trim();
+        if path.is_empty() {
+            return Err(ThemerError::ConfigOverrideError {
+                key: s.to_string(),
+                e: String::from("Override has no key"),
+            });
+        }
+
+        Ok(Self::parse(path, value_str, ConfigOrigin::Cli(String::from("--set"))))
+    }
+}
+
+// One step of a dotted path, which is either a key in a table, or an index into an array
+enum PathSegment {
+    Key(String),
+    Index(usize),
+}
+
+/// # Documentation
+/// Split a dotted path into its keys and array indices (e.g: `themes[0].volume` or `themes.0.volume`)
+fn parse_path_segments<S: AsRef<str>>(path: S) -> Result<Vec<PathSegment>, String> {
+    let mut segments = Vec::new();
+
+    for part in path.as_ref().split('.') {
+        let (key, indices) = part.split_once('[').map_or((part, ""), |(key, rest)| (key, rest));
+
+        if key.is_empty() && indices.is_empty() {
+            return Err(String::from("Path contains an empty key"));
+        }
+
+        if !key.is_empty() {
+            segments.push(
+                key.parse()
+                    .map_or_else(|_| PathSegment::Key(key.to_string()), PathSegment::Index),
+            );
+        }
+
+        // Any indices follow the key (e.g: `[0]` or `[0][1]`)
+        if !indices.is_empty() {
+            for index in format!("[{indices}").split('[').skip(1) {
+                let index = index
+                    .strip_suffix(']')
+                    .and_then(|index| index.parse().ok())
+                    .ok_or_else(|| format!("'[{index}' is not a valid array index"))?;
+
+                segments.push(PathSegment::Index(index));
+            }
+        }
+    }
+
+    Ok(segments)
+}
+
+/// # Documentation
+/// Add `key` to the end of a dotted path
+fn join_path<S: AsRef<str>>(prefix: S, key: &str) -> String {
+    if prefix.as_ref().is_empty() {
+        key.to_string()
+    } else {
+        format!("{}.{key}", prefix.as_ref())
+    }
+}
+
+/// # Documentation
+/// Merge `layer` into `base`, where tables are merged key by key and every other value is replaced.
+/// An empty table replaces the table below it, which is how `mapping = {}` removes every inherited mapping entry
+pub fn merge_toml_table(base: &mut Table, layer: &Table, origin: &ConfigOrigin, origins: &mut ConfigOrigins) {
+    merge_toml_table_at(base, layer, "", origin, origins);
+}
+
+fn merge_toml_table_at(base: &mut Table, layer: &Table, prefix: &str, origin: &ConfigOrigin, origins: &mut ConfigOrigins) {
+    for (key, value) in layer {
+        let path = join_path(prefix, key);
+
+        match (base.get_mut(key), value) {
+            (Some(Value::Table(base_table)), Value::Table(table)) if !table.is_empty() && key == "mapping" => {
+                merge_mapping_table(base_table, table, &path, origin, origins);
+            }
+            (Some(Value::Table(base_table)), Value::Table(table)) if !table.is_empty() => {
+                merge_toml_table_at(base_table, table, &path, origin, origins);
+            }
+            _ => {
+                base.insert(key.clone(), value.clone());
+                origins.set(path, origin.clone());
+            }
+        }
+    }
+}
+
+// Mapping entries are replaced as a whole, and the keys of the higher layer are moved to the front, so its rules are
+// checked before the rules of the layers below (The same as `Mapping::layered_over()`)
+fn merge_mapping_table(base: &mut Table, layer: &Table, prefix: &str, origin: &ConfigOrigin, origins: &mut ConfigOrigins) {
+    let mut merged = layer.clone();
+    for (key, value) in base.iter() {
+        if !merged.contains_key(key) {
+            merged.insert(key.clone(), value.clone());
+        }
+    }
+
+    for key in layer.keys() {
+        origins.set(join_path(prefix, key), origin.clone());
+    }
+
+    *base = merged;
+}
+
+/// # Documentation
+/// Set the value at the dotted path of `config_override`, creating any tables which don't exist yet
+///
+/// # Errors
+/// Returns an error if the path is empty or invalid
+/// Returns an error if the path indexes into a value which isn't an array, or past the end of an array
+/// Returns an error if the path goes through a value which isn't a table
+pub fn apply_config_override(
+    base: &mut Table,
+    config_override: &ConfigOverride,
+    origins: &mut ConfigOrigins,
+) -> Result<(), ThemerError> {
+    let to_override_error = |e: String| ThemerError::ConfigOverrideError {
+        key: config_override.path.clone(),
+        e,
+    };
+
+    let segments = parse_path_segments(&config_override.path).map_err(to_override_error)?;
+    let Some((PathSegment::Key(first_key), rest)) = segments.split_first() else {
+        return Err(to_override_error(String::from("Path must start with a key")));
+    };
+
+    // Walk down the path, formatting it in the same way as `config show --origin` as it goes
+    let mut path = first_key.clone();
+    let mut value = base.entry(first_key.clone()).or_insert_with(|| Value::Table(Table::new()));
+
+    for segment in rest {
+        value = match (segment, value) {
+            (PathSegment::Key(key), Value::Table(table)) => {
+                path = join_path(&path, key);
+                table.entry(key.clone()).or_insert_with(|| Value::Table(Table::new()))
+            }
+            (PathSegment::Index(index), Value::Array(array)) => {
+                let len = array.len();
+                path = format!("{path}[{index}]");
+                array
+                    .get_mut(*index)
+                    .ok_or_else(|| to_override_error(format!("Index {index} is out of range for an array of length {len}")))?
+            }
+            (PathSegment::Index(index), Value::Table(table)) => {
+                // Numeric keys are allowed in tables (e.g: a mapping key of `404`)
+                path = join_path(&path, &index.to_string());
+                table.entry(index.to_string()).or_insert_with(|| Value::Table(Table::new()))
+            }
+            (_, other) => {
+                return Err(to_override_error(format!(
+                    "'{path}' is {}, so it can't contain other values",
+                    other.type_str()
+                )));
+            }
+        };
+    }
+
+    *value = config_override.value.clone();
+    origins.set(path, config_override.origin.clone());
+
+    Ok(())
+}
+
+/// # Documentation
+/// Get every value in `table` with its dotted path, where tables and arrays of tables are expanded into their values
+#[must_use]
+pub fn flatten_toml_table(table: &Table) -> Vec<(String, Value)> {
+    let mut values = Vec::new();
+    flatten_toml_table_at(table, "", &mut values);
+
+    values
+}
+
+fn flatten_toml_table_at(table: &Table, prefix: &str, values: &mut Vec<(String, Value)>) {
+    for (key, value) in table {
+        let path = join_path(prefix, key);
+
+        match value {
+            Value::Table(inner) if !inner.is_empty() => flatten_toml_table_at(inner, &path, values),
+            Value::Array(array) if !array.is_empty() && array.iter().all(Value::is_table) => {
+                for (i, item) in array.iter().enumerate() {
+                    if let Value::Table(inner) = item {
+                        flatten_toml_table_at(inner, &format!("{path}[{i}]"), values);
+                    }
+                }
+            }
+            _ => values.push((path, value.clone())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_table(table_str: &str) -> Result<Table, ThemerError> {
+        toml::from_str(table_str).map_err(|e| ThemerError::ConfigOverrideError {
+            key: table_str.to_string(),
+            e: e.to_string(),
+        })
+    }
+
+    fn apply_override(base: &mut Table, override_str: &str) -> Result<ConfigOrigins, ThemerError> {
+        let mut origins = ConfigOrigins::default();
+        apply_config_override(base, &ConfigOverride::from_str(override_str)?, &mut origins)?;
+
+        Ok(origins)
+    }
+
+    #[test]
+    fn override_values_are_read_as_toml() -> Result<(), ThemerError> {
+        let config_override = ConfigOverride::from_str(" volume = 0.8")?;
+        assert_eq!(config_override.path, "volume");
+        assert_eq!(config_override.value, Value::Float(0.8));
+        assert_eq!(config_override.origin, ConfigOrigin::Cli(String::from("--set")));
+
+        // Only the first '=' separates the path, and anything which isn't valid TOML is a plain string
+        assert_eq!(
+            ConfigOverride::from_str("player.command=play --x=1 {path}")?.value,
+            Value::String(String::from("play --x=1 {path}"))
+        );
+        assert_eq!(
+            ConfigOverride::from_str("player.backend=paplay")?.value,
+            Value::String(String::from("paplay"))
+        );
+        assert_eq!(
+            ConfigOverride::from_str(r#"theme_name="a b""#)?.value,
+            Value::String(String::from("a b"))
+        );
+        assert_eq!(
+            ConfigOverride::from_str(r#"search_paths=["/a", "/b"]"#)?.value,
+            Value::Array(vec![Value::String(String::from("/a")), Value::String(String::from("/b"))])
+        );
+
+        assert!(ConfigOverride::from_str("volume").is_err());
+        assert!(ConfigOverride::from_str(" =0.8").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn overrides_follow_dotted_and_indexed_paths() -> Result<(), ThemerError> {
+        let mut base = parse_table(
+            r#"
+volume = 1.0
+[[themes]]
+name = "first"
+[[themes]]
+name = "second"
+"#,
+        )?;
+
+        let origins = apply_override(&mut base, "themes[1].volume=0.5")?;
+        assert_eq!(
+            flatten_toml_table(&base).last(),
+            Some(&(String::from("themes[1].volume"), Value::Float(0.5)))
+        );
+        assert_eq!(origins.get("themes[1].volume"), ConfigOrigin::Cli(String::from("--set")));
+        assert_eq!(origins.get("themes[0].volume"), ConfigOrigin::Default);
+
+        // A dotted index is the same as a bracketed one
+        apply_override(&mut base, "themes.0.name=renamed")?;
+        assert_eq!(
+            flatten_toml_table(&base).get(1),
+            Some(&(String::from("themes[0].name"), Value::String(String::from("renamed"))))
+        );
+
+        // Missing tables are created, and numeric keys are allowed in tables
+        apply_override(&mut base, "mapping.404=not-found")?;
+        apply_override(&mut base, r#"player.backend="aplay""#)?;
+        let values = flatten_toml_table(&base);
+        assert!(values.contains(&(String::from("mapping.404"), Value::String(String::from("not-found")))));
+        assert!(values.contains(&(String::from("player.backend"), Value::String(String::from("aplay")))));
+
+        assert!(apply_override(&mut base, "themes[2].volume=0.5").is_err());
+        assert!(apply_override(&mut base, "volume.inner=0.5").is_err());
+        assert!(apply_override(&mut base, "themes[x].volume=0.5").is_err());
+        assert!(apply_override(&mut base, "player..backend=aplay").is_err());
+        assert!(apply_override(&mut base, "[0]=aplay").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn layers_merge_tables_and_mapping_entries() -> Result<(), ThemerError> {
+        let mut base = parse_table(
+            r#"
+volume = 1.0
+search_paths = ["/a"]
+[player]
+backend = "auto"
+command = "play {path}"
+[mapping]
+bell = { name = "bell", volume = 0.5 }
+message = "message"
+"#,
+        )?;
+        let layer = parse_table(
+            r#"
+search_paths = ["/b"]
+[player]
+backend = "aplay"
+[mapping]
+bell = "other-bell"
+"ci-*" = "dialog-$1"
+"#,
+        )?;
+
+        let origin = ConfigOrigin::File(String::from("layer.toml"));
+        let mut origins = ConfigOrigins::default();
+        merge_toml_table(&mut base, &layer, &origin, &mut origins);
+
+        // Tables are merged key by key, while other values are replaced
+        let expected = parse_table(
+            r#"
+volume = 1.0
+search_paths = ["/b"]
+[player]
+backend = "aplay"
+command = "play {path}"
+[mapping]
+bell = "other-bell"
+"ci-*" = "dialog-$1"
+message = "message"
+"#,
+        )?;
+        assert_eq!(base, expected);
+
+        // Mapping entries are replaced as a whole, with the keys of the layer moved to the front
+        let mapping_keys = base
+            .get("mapping")
+            .and_then(Value::as_table)
+            .map(|mapping| mapping.keys().cloned().collect::<Vec<_>>());
+        assert_eq!(
+            mapping_keys,
+            Some(vec![String::from("bell"), String::from("ci-*"), String::from("message")])
+        );
+
+        assert_eq!(origins.get("mapping.bell"), origin);
+        assert_eq!(origins.get("player.backend"), origin);
+        assert_eq!(origins.get("player.command"), ConfigOrigin::Default);
+        assert_eq!(origins.get("mapping.message"), ConfigOrigin::Default);
+
+        // An empty mapping replaces every entry below it
+        merge_toml_table(&mut base, &parse_table("mapping = {}")?, &origin, &mut origins);
+        assert_eq!(base.get("mapping"), Some(&Value::Table(Table::new())));
+
+        Ok(())
+    }
+}
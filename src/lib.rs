@@ -5,10 +5,24 @@
 #![warn(clippy::expect_used)]
 #![allow(clippy::cast_possible_truncation)]
 
+pub mod atomic_file;
+pub mod cache;
+pub mod canberra;
 pub mod cli;
 pub mod config;
+pub mod daemon;
+#[cfg(feature = "dbus")]
+pub mod dbus;
+pub mod decode;
 pub mod duration;
 pub mod error;
+pub mod index_theme;
+pub mod layer;
+pub mod locale;
 pub mod mapping;
+pub mod sink;
 pub mod sound;
+pub mod state;
 pub mod theme;
+pub mod volume;
+pub mod xdg;
@@ -0,0 +1,120 @@
+use std::sync::Mutex;
+
+use crate::error::ThemerError;
+
+/// # Documentation
+/// The environment variables which set the locale for messages, in order of precedence
+pub const LOCALE_ENV_VARS: &[&str] = &["LC_ALL", "LC_MESSAGES", "LANG"];
+
+static SELECTED_LOCALE: Mutex<Option<String>> = Mutex::new(None);
+
+/// # Documentation
+/// Override the locale which is read from the environment, `None` removes the override
+///
+/// # Errors
+/// Returns an error if `SELECTED_LOCALE` couldn't be locked
+pub fn select_locale(locale: Option<String>) -> Result<(), ThemerError> {
+    *SELECTED_LOCALE
+        .lock()
+        .map_err(|e| ThemerError::MutexLockError(e.to_string()))? = locale;
+
+    Ok(())
+}
+
+/// # Documentation
+/// Get the selected locale, otherwise the first locale set by `$LC_ALL`, `$LC_MESSAGES`, or `$LANG`
+///
+/// # Errors
+/// Returns an error if `SELECTED_LOCALE` couldn't be locked
+pub fn get_locale() -> Result<Option<String>, ThemerError> {
+    let selected_locale = SELECTED_LOCALE
+        .lock()
+        .map_err(|e| ThemerError::MutexLockError(e.to_string()))?
+        .clone();
+
+    Ok(selected_locale.or_else(|| {
+        LOCALE_ENV_VARS
+            .iter()
+            .filter_map(|var| std::env::var(var).ok())
+            .find(|locale| !locale.is_empty())
+    }))
+}
+
+/// # Documentation
+/// Get the directory names to search for a locale, from most to least specific.
+/// `lang_COUNTRY.ENCODING@modifier` gives `lang_COUNTRY@modifier`, `lang_COUNTRY`, then `lang`
+#[must_use]
+pub fn get_locale_variants<S: AsRef<str>>(locale: S) -> Vec<String> {
+    let (locale, modifier) = locale
+        .as_ref()
+        .split_once('@')
+        .map_or_else(|| (locale.as_ref(), None), |(locale, modifier)| (locale, Some(modifier)));
+
+    // The encoding isn't used in directory names
+    let locale = locale.split_once('.').map_or(locale, |(locale, _)| locale);
+
+    // The C and POSIX locales have no translations
+    if locale.is_empty() || locale == "C" || locale == "POSIX" {
+        return Vec::new();
+    }
+
+    let lang = locale.split_once('_').map_or(locale, |(lang, _)| lang);
+
+    let mut variants = Vec::new();
+    for variant in [
+        modifier.map(|modifier| format!("{locale}@{modifier}")),
+        Some(locale.to_string()),
+        Some(lang.to_string()),
+    ]
+    .into_iter()
+    .flatten()
+    {
+        if !variants.contains(&variant) {
+            variants.push(variant);
+        }
+    }
+
+    variants
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn to_strings(strs: &[&str]) -> Vec<String> {
+        strs.iter().map(ToString::to_string).collect()
+    }
+
+    #[test]
+    fn locale_variants_go_from_most_to_least_specific() {
+        assert_eq!(
+            get_locale_variants("de_DE.UTF-8@euro"),
+            to_strings(&["de_DE@euro", "de_DE", "de"])
+        );
+        assert_eq!(get_locale_variants("pt_BR.UTF-8"), to_strings(&["pt_BR", "pt"]));
+        assert_eq!(get_locale_variants("sr@latin"), to_strings(&["sr@latin", "sr"]));
+        assert_eq!(get_locale_variants("fr"), to_strings(&["fr"]));
+    }
+
+    #[test]
+    fn untranslated_locales_have_no_variants() {
+        for locale in ["C", "POSIX", "C.UTF-8", "", ".UTF-8"] {
+            assert!(
+                get_locale_variants(locale).is_empty(),
+                "'{locale}' gave {:?}",
+                get_locale_variants(locale)
+            );
+        }
+    }
+
+    #[test]
+    fn selected_locale_overrides_the_environment() -> Result<(), ThemerError> {
+        select_locale(Some(String::from("nl_NL.UTF-8")))?;
+        let locale = get_locale();
+        select_locale(None)?;
+
+        assert_eq!(locale?.as_deref(), Some("nl_NL.UTF-8"));
+
+        Ok(())
+    }
+}
@@ -1,14 +1,30 @@
+use std::path::Path;
+
 use clap::Parser;
 
 use sound_themer::cli::{Cli, evaluate_cli};
 
 // TODO Add modularity to sound playing so other commands can be used
 
+// When the binary is called through a link with this name, it acts as the `canberra-gtk-play` subcommand
+const CANBERRA_GTK_PLAY_NAME: &str = "canberra-gtk-play";
+
 fn main() {
+    let mut args = std::env::args_os().collect::<Vec<_>>();
+
+    if args
+        .first()
+        .and_then(|arg| Path::new(arg).file_name())
+        .is_some_and(|name| name == CANBERRA_GTK_PLAY_NAME)
+    {
+        args.insert(1, CANBERRA_GTK_PLAY_NAME.into());
+    }
+
     // Parse the CLI arguments
-    let cli = Cli::parse();
+    let cli = Cli::parse_from(args);
 
     if let Err(e) = evaluate_cli(&cli, std::io::stdout()) {
         eprintln!("Error: {e}");
+        std::process::exit(1);
     }
 }
@@ -1,14 +1,29 @@
 use std::{
-    collections::HashMap,
+    collections::{HashMap, HashSet},
+    fmt,
     ops::{Deref, DerefMut},
     str::FromStr,
 };
 
-use serde::{Deserialize, de::IntoDeserializer};
-use strum::{EnumIter, IntoEnumIterator};
+use regex::Regex;
+use serde::{
+    Deserialize, Deserializer, Serialize, Serializer,
+    de::{self, IntoDeserializer, MapAccess, Visitor, value::MapAccessDeserializer},
+    ser::SerializeMap,
+};
+use strum::{Display, EnumIter, IntoEnumIterator};
+
+use crate::{
+    duration::{PlaybackDuration, PlaybackOffset, PlaybackRange},
+    error::ThemerError,
+    volume::Volume,
+};
 
-#[derive(Debug, Deserialize, PartialEq, Eq, Hash, EnumIter, Clone, Copy)]
+/// # Documentation
+/// The keys which are mapped by default, any other key can also be added to a mapping
+#[derive(Debug, Deserialize, PartialEq, Eq, Hash, EnumIter, Display, Clone, Copy)]
 #[serde(rename_all = "kebab-case")]
+#[strum(serialize_all = "kebab-case")]
 pub enum MappingKey {
     AudioChange,
     Login,
@@ -58,53 +73,975 @@ fn get_default_mapping_key_value(key: MappingKey) -> MappingEntry {
     )
 }
 
-#[derive(Debug, Deserialize, PartialEq, Eq, Clone)]
-pub struct Mapping(HashMap<MappingKey, MappingEntry>);
+/// # Documentation
+/// A mapping from any key to the sound which is played for it.
+/// Mappings are layered over each other (Default mapping < `[mapping]` < extended theme < theme), so each layer only
+/// needs to set the entries which it changes
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct Mapping {
+    entries: HashMap<String, MappingEntry>,
+    /// # Documentation
+    /// The glob and regex rules, in the order they are checked after `entries`
+    rules: Vec<MappingRule>,
+    /// # Documentation
+    /// The keys which are removed from the layers below, set using `key = false`
+    removed: HashSet<String>,
+    /// # Documentation
+    /// Whether the layers below are removed entirely, set using `mapping = {}`
+    replaces: bool,
+    /// # Documentation
+    /// The keys which each alias passed through to reach its entry, set by `resolve_aliases()`
+    alias_chains: HashMap<String, Vec<String>>,
+}
 
 impl Deref for Mapping {
-    type Target = HashMap<MappingKey, MappingEntry>;
+    type Target = HashMap<String, MappingEntry>;
 
     fn deref(&self) -> &Self::Target {
-        &self.0
+        &self.entries
     }
 }
 
 impl DerefMut for Mapping {
     fn deref_mut(&mut self) -> &mut Self::Target {
-        &mut self.0
+        &mut self.entries
     }
 }
 
-impl Default for Mapping {
-    fn default() -> Self {
-        Self(
-            MappingKey::iter()
-                .map(|key| (key, get_default_mapping_key_value(key)))
+impl Mapping {
+    /// # Documentation
+    /// Get the default mapping, which every other mapping is layered over
+    #[must_use]
+    pub fn builtin() -> Self {
+        Self {
+            entries: MappingKey::iter()
+                .map(|key| (key.to_string(), get_default_mapping_key_value(key)))
                 .collect(),
-        )
+            ..Self::default()
+        }
+    }
+
+    /// # Documentation
+    /// Get the mapping which results from layering this mapping over `base`
+    #[must_use]
+    pub fn layered_over(&self, base: &Self) -> Self {
+        let (mut entries, base_rules) = if self.replaces {
+            (HashMap::new(), Vec::new())
+        } else {
+            (base.entries.clone(), base.rules.clone())
+        };
+
+        entries.retain(|key, _| !self.removed.contains(key));
+        entries.extend(self.entries.clone());
+
+        // The rules of this layer are checked before the rules of the layers below
+        let mut rules = self.rules.clone();
+        rules.extend(base_rules.into_iter().filter(|base_rule| {
+            !self.removed.contains(&base_rule.key) && !self.rules.iter().any(|rule| rule.key == base_rule.key)
+        }));
+
+        Self {
+            entries,
+            rules,
+            ..Self::default()
+        }
+    }
+
+    /// # Documentation
+    /// Get the glob and regex rules, in the order they are checked
+    #[must_use]
+    pub fn rules(&self) -> &[MappingRule] {
+        &self.rules
+    }
+
+    /// # Documentation
+    /// Whether layering the mapping over another mapping leaves it unchanged, so it doesn't need to be written
+    #[must_use]
+    pub fn is_empty_layer(&self) -> bool {
+        self.entries.is_empty() && self.rules.is_empty() && self.removed.is_empty() && !self.replaces
+    }
+
+    /// # Documentation
+    /// Get the entry for `sound_name`, using its exact key if it is mapped, otherwise the first rule which matches it
+    #[must_use]
+    pub fn find<S: AsRef<str>>(&self, sound_name: S) -> Option<MappingEntry> {
+        self.entries.get(sound_name.as_ref()).cloned().or_else(|| {
+            self.get_matching_rule(sound_name.as_ref())
+                .and_then(|rule| rule.apply(sound_name.as_ref()))
+        })
+    }
+
+    /// # Documentation
+    /// Get the first rule which matches `sound_name`, if it isn't mapped by an exact key
+    #[must_use]
+    pub fn get_matching_rule<S: AsRef<str>>(&self, sound_name: S) -> Option<&MappingRule> {
+        if self.entries.contains_key(sound_name.as_ref()) {
+            return None;
+        }
+
+        self.rules.iter().find(|rule| rule.regex.is_match(sound_name.as_ref()))
+    }
+}
+
+impl Mapping {
+    /// # Documentation
+    /// Replace each alias (An entry whose name is `@other-key`) with the entry of the key it points to, following
+    /// aliases of aliases. An alias to a key which isn't mapped plays that key as a sound name
+    ///
+    /// # Errors
+    /// Returns an error if the aliases form a cycle
+    /// Returns an error if an alias is used alongside other names in `names`
+    pub fn resolve_aliases(&mut self) -> Result<(), ThemerError> {
+        // Check the keys in order, so the same cycle is always reported
+        let mut keys = self.entries.keys().cloned().collect::<Vec<_>>();
+        keys.sort();
+
+        // Every alias is followed through the unresolved entries, before any of them are replaced
+        let mut resolved_entries = Vec::new();
+        for key in keys {
+            if let Some(entry) = self.entries.get(&key)
+                && let Some((resolved_entry, chain)) = self.follow_alias(&key, entry)?
+            {
+                resolved_entries.push((key, resolved_entry, chain));
+            }
+        }
+
+        for i in 0..self.rules.len() {
+            if let Some((resolved_entry, _)) = self.follow_alias(&self.rules[i].key, &self.rules[i].entry)? {
+                self.rules[i].entry = resolved_entry;
+            }
+        }
+
+        for (key, resolved_entry, chain) in resolved_entries {
+            self.entries.insert(key.clone(), resolved_entry);
+            self.alias_chains.insert(key, chain);
+        }
+
+        Ok(())
+    }
+
+    /// # Documentation
+    /// Follow the alias in `entry` until an entry which isn't an alias is found, returning the entry which results from
+    /// every alias in the chain pointing at it, and the keys which were passed through. Gives `None` if `entry` isn't an
+    /// alias
+    ///
+    /// # Errors
+    /// Returns an error if the aliases form a cycle
+    /// Returns an error if an alias is used alongside other names in `names`
+    fn follow_alias(&self, key: &str, entry: &MappingEntry) -> Result<Option<(MappingEntry, Vec<String>)>, ThemerError> {
+        if entry.candidates().len() > 1
+            && entry
+                .candidates()
+                .iter()
+                .any(|candidate| candidate.name.starts_with(ALIAS_PREFIX))
+        {
+            return Err(ThemerError::MappingAliasError {
+                key: key.to_string(),
+                e: String::from("An alias must be the only name of its entry"),
+            });
+        }
+
+        let Some(mut target) = entry.alias() else {
+            return Ok(None);
+        };
+
+        let mut chain = vec![key.to_string()];
+        let mut aliases = vec![entry];
+        let target_entry = loop {
+            if chain.contains(&target) {
+                chain.push(target);
+
+                return Err(ThemerError::MappingAliasError {
+                    key: key.to_string(),
+                    e: format!("Aliases form a cycle: {}", chain.join(" --> ")),
+                });
+            }
+
+            chain.push(target.clone());
+
+            match self.entries.get(&target) {
+                Some(target_entry) => match target_entry.alias() {
+                    Some(next_target) => {
+                        aliases.push(target_entry);
+                        target = next_target;
+                    }
+                    None => break target_entry.clone(),
+                },
+                None => break MappingEntry::Simple(target),
+            }
+        };
+
+        // The settings of each alias override those of the aliases it points through, so the first alias takes priority
+        let resolved_entry = aliases
+            .into_iter()
+            .rev()
+            .fold(target_entry, |target_entry, alias| alias.aliased_to(&target_entry));
+
+        Ok(Some((resolved_entry, chain)))
+    }
+
+    /// # Documentation
+    /// Get the keys which the alias `key` passed through to reach its entry, starting with `key`
+    #[must_use]
+    pub fn get_alias_chain<S: AsRef<str>>(&self, key: S) -> Option<&Vec<String>> {
+        self.alias_chains.get(key.as_ref())
+    }
+}
+
+impl<'de> Deserialize<'de> for Mapping {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        // An entry which is set to `false` removes the key from the layers below
+        struct MappingValue(Option<MappingEntry>);
+
+        impl<'de> Deserialize<'de> for MappingValue {
+            fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                struct ValueVisitor;
+
+                impl<'de> Visitor<'de> for ValueVisitor {
+                    type Value = MappingValue;
+
+                    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                        f.write_str("a sound name, a table with a `name` or `names` field, or `false`")
+                    }
+
+                    fn visit_bool<E: de::Error>(self, value: bool) -> Result<Self::Value, E> {
+                        if value {
+                            Err(de::Error::custom(
+                                "Only `false` can be used as a mapping entry, to remove the key",
+                            ))
+                        } else {
+                            Ok(MappingValue(None))
+                        }
+                    }
+
+                    fn visit_str<E: de::Error>(self, name: &str) -> Result<Self::Value, E> {
+                        MappingEntry::deserialize(name.into_deserializer()).map(|entry| MappingValue(Some(entry)))
+                    }
+
+                    fn visit_map<A: MapAccess<'de>>(self, map: A) -> Result<Self::Value, A::Error> {
+                        MappingEntry::deserialize(MapAccessDeserializer::new(map)).map(|entry| MappingValue(Some(entry)))
+                    }
+                }
+
+                deserializer.deserialize_any(ValueVisitor)
+            }
+        }
+
+        // The entries are kept in the order they were written, which is the order the rules are checked in
+        struct OrderedValues(Vec<(String, MappingValue)>);
+
+        impl<'de> Deserialize<'de> for OrderedValues {
+            fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                struct OrderedVisitor;
+
+                impl<'de> Visitor<'de> for OrderedVisitor {
+                    type Value = OrderedValues;
+
+                    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                        f.write_str("a table of mapping entries")
+                    }
+
+                    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+                        let mut values = Vec::new();
+                        while let Some(value) = map.next_entry()? {
+                            values.push(value);
+                        }
+
+                        Ok(OrderedValues(values))
+                    }
+                }
+
+                deserializer.deserialize_map(OrderedVisitor)
+            }
+        }
+
+        let OrderedValues(values) = OrderedValues::deserialize(deserializer)?;
+
+        // Keys are used as sound names, so they can't be empty or contain a path
+        if let Some((key, _)) = values.iter().find(|(key, _)| key.trim().is_empty() || key.contains('/')) {
+            return Err(de::Error::custom(format!(
+                "Mapping key '{key}' must be a sound name, which is not empty and doesn't contain '/'"
+            )));
+        }
+
+        let mut mapping = Self {
+            // An empty mapping removes the layers below
+            replaces: values.is_empty(),
+            ..Self::default()
+        };
+
+        for (key, MappingValue(entry)) in values {
+            match entry {
+                None => {
+                    mapping.removed.insert(key);
+                }
+                // Entries with a regex, or with a glob as their key, are rules
+                Some(entry) if entry.regex().is_some() || is_glob(&key) => {
+                    let pattern = entry.regex().unwrap_or_else(|| glob_to_regex(&key));
+                    let regex = Regex::new(&pattern)
+                        .map_err(|e| de::Error::custom(format!("Mapping rule '{key}' has an invalid pattern:\t{e}")))?;
+
+                    mapping.rules.push(MappingRule { key, regex, entry });
+                }
+                Some(entry) => {
+                    mapping.entries.insert(key, entry);
+                }
+            }
+        }
+
+        Ok(mapping)
+    }
+}
+
+impl Serialize for Mapping {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        // Exact keys are written in order, so the output doesn't change between runs
+        let mut entries = self.entries.iter().collect::<Vec<_>>();
+        entries.sort_by_key(|(key, _)| *key);
+
+        let mut map = serializer.serialize_map(Some(entries.len() + self.rules.len() + self.removed.len()))?;
+        for (key, entry) in entries {
+            map.serialize_entry(key, entry)?;
+        }
+        for rule in &self.rules {
+            map.serialize_entry(&rule.key, &rule.entry)?;
+        }
+
+        let mut removed = self.removed.iter().collect::<Vec<_>>();
+        removed.sort();
+        for key in removed {
+            map.serialize_entry(key, &false)?;
+        }
+
+        map.end()
+    }
+}
+
+/// # Documentation
+/// An entry which is used for any sound name matching its pattern, created from a glob key (e.g: `"ci-*-failed"`) or
+/// from an entry with a `regex`. Capture groups can be used in the entry's names (e.g: `$1`, or `${name}`)
+#[derive(Debug, Clone)]
+pub struct MappingRule {
+    pub key: String,
+    pub regex: Regex,
+    pub entry: MappingEntry,
+}
+
+impl PartialEq for MappingRule {
+    fn eq(&self, other: &Self) -> bool {
+        self.key == other.key && self.regex.as_str() == other.regex.as_str() && self.entry == other.entry
     }
 }
 
-#[derive(Clone, PartialEq, PartialOrd, Ord, Eq, Debug, Deserialize)]
+impl MappingRule {
+    /// # Documentation
+    /// Get the entry for `sound_name` if it matches the rule, with the capture groups in its names replaced
+    #[must_use]
+    pub fn apply<S: AsRef<str>>(&self, sound_name: S) -> Option<MappingEntry> {
+        let captures = self.regex.captures(sound_name.as_ref())?;
+
+        Some(self.entry.map_names(|name| {
+            let mut expanded_name = String::new();
+            captures.expand(name, &mut expanded_name);
+
+            expanded_name
+        }))
+    }
+}
+
+/// # Documentation
+/// Whether a mapping key is a glob pattern, which contains `*` or `?`
+#[must_use]
+pub fn is_glob<S: AsRef<str>>(key: S) -> bool {
+    key.as_ref().contains(['*', '?'])
+}
+
+/// # Documentation
+/// Convert a glob pattern into a regex which matches the whole name, where each `*` and `?` is a capture group
+#[must_use]
+pub fn glob_to_regex<S: AsRef<str>>(glob: S) -> String {
+    let pattern = glob
+        .as_ref()
+        .chars()
+        .map(|c| match c {
+            '*' => String::from("(.*)"),
+            '?' => String::from("(.)"),
+            c => regex::escape(&c.to_string()),
+        })
+        .collect::<String>();
+
+    format!("^{pattern}$")
+}
+
+/// # Documentation
+/// How a sound is picked from the names of a mapping entry each time it is played
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum PickMode {
+    #[default]
+    Random,
+    Weighted,
+    RoundRobin,
+    FirstExisting,
+}
+
+/// # Documentation
+/// One of the sound names a mapping entry can pick from, with its weight for `PickMode::Weighted`
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(from = "CandidateValue", into = "CandidateValue")]
+pub struct MappingCandidate {
+    pub name: String,
+    pub weight: f32,
+}
+
+impl MappingCandidate {
+    pub fn new<S: AsRef<str>>(name: S) -> Self {
+        Self {
+            name: name.as_ref().to_string(),
+            weight: DEFAULT_CANDIDATE_WEIGHT,
+        }
+    }
+}
+
+// A candidate can be written as just its name, or as a table which also sets its weight
+#[derive(Serialize, Deserialize)]
 #[serde(untagged)]
+enum CandidateValue {
+    Name(String),
+    Weighted {
+        name: String,
+        #[serde(default = "get_default_candidate_weight")]
+        weight: f32,
+    },
+}
+
+impl From<CandidateValue> for MappingCandidate {
+    fn from(value: CandidateValue) -> Self {
+        match value {
+            CandidateValue::Name(name) => Self::new(name),
+            CandidateValue::Weighted { name, weight } => Self { name, weight },
+        }
+    }
+}
+
+impl From<MappingCandidate> for CandidateValue {
+    fn from(candidate: MappingCandidate) -> Self {
+        // The weight is only written when it isn't the default
+        if (candidate.weight - DEFAULT_CANDIDATE_WEIGHT).abs() < f32::EPSILON {
+            Self::Name(candidate.name)
+        } else {
+            Self::Weighted {
+                name: candidate.name,
+                weight: candidate.weight,
+            }
+        }
+    }
+}
+
+const DEFAULT_CANDIDATE_WEIGHT: f32 = 1.0;
+/// # Documentation
+/// The prefix of a mapping name which points at another mapping key, instead of a sound (e.g: `error = "@dialog-error"`)
+pub const ALIAS_PREFIX: char = '@';
+
+const fn get_default_candidate_weight() -> f32 {
+    DEFAULT_CANDIDATE_WEIGHT
+}
+
+/// # Documentation
+/// One step of a sequence mapping entry, which is either a sound (Played with its own duration, volume, fade-out, and
+/// range), or a gap of silence (e.g: `{ name = "message", duration = "50%" }`, `{ gap = "80ms" }`)
+#[derive(Serialize, Clone, PartialEq, Debug)]
+#[serde(untagged)]
+pub enum SequenceStep {
+    Sound {
+        name: String,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        duration: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        volume: Option<Volume>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        fade_out: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        range: Option<String>,
+    },
+    Gap {
+        gap: String,
+    },
+}
+
+impl<'de> Deserialize<'de> for SequenceStep {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(deny_unknown_fields)]
+        struct StepEntry {
+            #[serde(default)]
+            name: Option<String>,
+            #[serde(default)]
+            gap: Option<String>,
+            #[serde(default)]
+            duration: Option<String>,
+            #[serde(default)]
+            volume: Option<Volume>,
+            #[serde(default)]
+            fade_out: Option<String>,
+            #[serde(default)]
+            range: Option<String>,
+        }
+
+        let step = StepEntry::deserialize(deserializer)?;
+
+        // Each step is checked here, so a mistake is reported when the config is loaded instead of when it is played
+        match (step.name, step.gap) {
+            (Some(name), None) => {
+                if name.starts_with(ALIAS_PREFIX) {
+                    return Err(de::Error::custom(
+                        "A sequence step can't be an alias, since its name is already looked up in the mapping",
+                    ));
+                }
+
+                for playback_duration in [&step.duration, &step.fade_out].into_iter().flatten() {
+                    PlaybackDuration::from_str(playback_duration).map_err(de::Error::custom)?;
+                }
+                if let Some(range) = &step.range {
+                    PlaybackRange::from_str(range).map_err(de::Error::custom)?;
+                }
+
+                Ok(Self::Sound {
+                    name,
+                    duration: step.duration,
+                    volume: step.volume,
+                    fade_out: step.fade_out,
+                    range: step.range,
+                })
+            }
+            (None, Some(gap)) => {
+                if step.duration.is_some() || step.volume.is_some() || step.fade_out.is_some() || step.range.is_some() {
+                    return Err(de::Error::custom("A gap step can only have a `gap`"));
+                }
+
+                PlaybackDuration::from_str(&gap)
+                    .and_then(|playback_duration| playback_duration.to_gap())
+                    .map_err(de::Error::custom)?;
+
+                Ok(Self::Gap { gap })
+            }
+            (Some(_), Some(_)) => Err(de::Error::custom("A sequence step can't have both `name` and `gap`")),
+            (None, None) => Err(de::Error::custom("A sequence step must have either a `name` or a `gap`")),
+        }
+    }
+}
+
+impl std::fmt::Display for SequenceStep {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Sound { name, .. } => write!(f, "{name}"),
+            Self::Gap { gap } => write!(f, "{gap} gap"),
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Debug)]
 pub enum MappingEntry {
     Simple(String),
-    Detailed { name: String, duration: String },
+    Detailed {
+        names: Vec<MappingCandidate>,
+        pick: PickMode,
+        duration: Option<String>,
+        volume: Option<Volume>,
+        fade_out: Option<String>,
+        start: Option<String>,
+        end: Option<String>,
+        range: Option<String>,
+        regex: Option<String>,
+    },
+    /// # Documentation
+    /// A short composition of sounds and gaps which are played one after another, where each sound is looked up in the
+    /// mapping and theme in the same way as any other sound name
+    Sequence(Vec<SequenceStep>),
 }
 
 impl MappingEntry {
+    #[must_use]
+    pub const fn is_sequence(&self) -> bool {
+        matches!(self, Self::Sequence(_))
+    }
+
+    /// # Documentation
+    /// Get the key which this entry is an alias of, if its only name starts with `ALIAS_PREFIX`
+    #[must_use]
+    pub fn alias(&self) -> Option<String> {
+        match self.candidates().as_slice() {
+            [candidate] if !self.is_sequence() => candidate.name.strip_prefix(ALIAS_PREFIX).map(ToString::to_string),
+            _ => None,
+        }
+    }
+
+    /// # Documentation
+    /// Get the entry which results from this alias pointing at `target`, where the names come from `target`, and the
+    /// playback settings of this entry override those of `target`
+    #[must_use]
+    pub fn aliased_to(&self, target: &Self) -> Self {
+        match (self, target) {
+            // The steps of a sequence set their own playback settings
+            (Self::Simple(_) | Self::Sequence(_), _) | (_, Self::Sequence(_)) => target.clone(),
+            (
+                Self::Detailed {
+                    duration,
+                    volume,
+                    fade_out,
+                    start,
+                    end,
+                    range,
+                    regex,
+                    ..
+                },
+                _,
+            ) => Self::Detailed {
+                names: target.candidates(),
+                pick: target.pick(),
+                duration: duration.clone().or_else(|| target.duration()),
+                volume: volume.or_else(|| target.volume()),
+                fade_out: fade_out.clone().or_else(|| target.fade_out()),
+                start: start.clone().or_else(|| target.start()),
+                end: end.clone().or_else(|| target.end()),
+                range: range.clone().or_else(|| target.raw_range()),
+                regex: regex.clone(),
+            },
+        }
+    }
+
+    /// # Documentation
+    /// Get the entry with `f` applied to each of its names
+    #[must_use]
+    pub fn map_names<F: Fn(&str) -> String>(&self, f: F) -> Self {
+        match self {
+            Self::Simple(name) => Self::Simple(f(name)),
+            Self::Detailed { names, .. } => {
+                let mut entry = self.clone();
+                if let Self::Detailed { names: entry_names, .. } = &mut entry {
+                    *entry_names = names
+                        .iter()
+                        .map(|candidate| MappingCandidate {
+                            name: f(&candidate.name),
+                            weight: candidate.weight,
+                        })
+                        .collect();
+                }
+
+                entry
+            }
+            Self::Sequence(steps) => Self::Sequence(
+                steps
+                    .iter()
+                    .map(|step| {
+                        let mut step = step.clone();
+                        if let SequenceStep::Sound { name, .. } = &mut step {
+                            *name = f(name);
+                        }
+
+                        step
+                    })
+                    .collect(),
+            ),
+        }
+    }
+
+    /// # Documentation
+    /// Get the regex which the entry is matched with, instead of its key
+    #[must_use]
+    pub fn regex(&self) -> Option<String> {
+        match self {
+            Self::Simple(_) | Self::Sequence(_) => None,
+            Self::Detailed { regex, .. } => regex.clone(),
+        }
+    }
+
+    /// # Documentation
+    /// Get the first of the entry's sound names
     #[must_use]
     pub fn name(&self) -> String {
-        match self.clone() {
-            Self::Simple(name) | Self::Detailed { name, duration: _ } => name,
+        match self {
+            Self::Simple(name) => name.clone(),
+            Self::Detailed { names, .. } => names.first().map(|candidate| candidate.name.clone()).unwrap_or_default(),
+            Self::Sequence(_) => self
+                .candidates()
+                .first()
+                .map(|candidate| candidate.name.clone())
+                .unwrap_or_default(),
+        }
+    }
+
+    /// # Documentation
+    /// Get every sound name which the entry can pick from, or every sound name in a sequence
+    #[must_use]
+    pub fn candidates(&self) -> Vec<MappingCandidate> {
+        match self {
+            Self::Simple(name) => vec![MappingCandidate::new(name)],
+            Self::Detailed { names, .. } => names.clone(),
+            Self::Sequence(steps) => steps
+                .iter()
+                .filter_map(|step| match step {
+                    SequenceStep::Sound { name, .. } => Some(MappingCandidate::new(name)),
+                    SequenceStep::Gap { .. } => None,
+                })
+                .collect(),
+        }
+    }
+
+    #[must_use]
+    pub const fn pick(&self) -> PickMode {
+        match self {
+            Self::Simple(_) | Self::Sequence(_) => PickMode::FirstExisting,
+            Self::Detailed { pick, .. } => *pick,
         }
     }
 
     #[must_use]
     pub fn duration(&self) -> Option<String> {
-        match self.clone() {
-            Self::Simple(_) => None,
-            Self::Detailed { name: _, duration } => Some(duration),
+        match self {
+            Self::Simple(_) | Self::Sequence(_) => None,
+            Self::Detailed { duration, .. } => duration.clone(),
+        }
+    }
+
+    #[must_use]
+    pub const fn volume(&self) -> Option<Volume> {
+        match self {
+            Self::Simple(_) | Self::Sequence(_) => None,
+            Self::Detailed { volume, .. } => *volume,
+        }
+    }
+
+    #[must_use]
+    pub fn fade_out(&self) -> Option<String> {
+        match self {
+            Self::Simple(_) | Self::Sequence(_) => None,
+            Self::Detailed { fade_out, .. } => fade_out.clone(),
+        }
+    }
+
+    #[must_use]
+    pub fn start(&self) -> Option<String> {
+        match self {
+            Self::Simple(_) | Self::Sequence(_) => None,
+            Self::Detailed { start, .. } => start.clone(),
+        }
+    }
+
+    #[must_use]
+    pub fn end(&self) -> Option<String> {
+        match self {
+            Self::Simple(_) | Self::Sequence(_) => None,
+            Self::Detailed { end, .. } => end.clone(),
+        }
+    }
+
+    /// # Documentation
+    /// Get the unparsed `range` of the entry, use `range()` to get the section of the sound which is played
+    #[must_use]
+    pub fn raw_range(&self) -> Option<String> {
+        match self {
+            Self::Simple(_) | Self::Sequence(_) => None,
+            Self::Detailed { range, .. } => range.clone(),
+        }
+    }
+
+    /// # Documentation
+    /// Get the section of the sound which is played, where `start` and `end` take priority over `range`
+    ///
+    /// # Errors
+    /// Returns an error if `start`, `end`, or `range` could not be parsed
+    pub fn range(&self) -> Result<PlaybackRange, ThemerError> {
+        match self {
+            Self::Simple(_) | Self::Sequence(_) => Ok(PlaybackRange::default()),
+            Self::Detailed { start, end, range, .. } => Ok(PlaybackRange {
+                start: start.as_deref().map(PlaybackOffset::from_str).transpose()?,
+                end: end.as_deref().map(PlaybackOffset::from_str).transpose()?,
+            }
+            .or(range.as_deref().map(PlaybackRange::from_str).transpose()?.unwrap_or_default())),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for MappingEntry {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        // Unknown fields are reported, instead of being ignored or failing to match either kind of entry
+        #[derive(Deserialize)]
+        #[serde(deny_unknown_fields)]
+        struct DetailedEntry {
+            #[serde(default)]
+            name: Option<String>,
+            #[serde(default)]
+            names: Option<Vec<MappingCandidate>>,
+            #[serde(default)]
+            pick: Option<PickMode>,
+            #[serde(default)]
+            duration: Option<String>,
+            #[serde(default)]
+            volume: Option<Volume>,
+            #[serde(default)]
+            fade_out: Option<String>,
+            #[serde(default)]
+            start: Option<String>,
+            #[serde(default)]
+            end: Option<String>,
+            #[serde(default)]
+            range: Option<String>,
+            #[serde(default)]
+            regex: Option<String>,
+            #[serde(default)]
+            sequence: Option<Vec<SequenceStep>>,
+        }
+
+        struct EntryVisitor;
+
+        impl<'de> Visitor<'de> for EntryVisitor {
+            type Value = MappingEntry;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a sound name, or a table with a `name`, `names`, or `sequence` field")
+            }
+
+            fn visit_str<E: de::Error>(self, name: &str) -> Result<Self::Value, E> {
+                Ok(MappingEntry::Simple(name.to_string()))
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, map: A) -> Result<Self::Value, A::Error> {
+                let entry = DetailedEntry::deserialize(MapAccessDeserializer::new(map))?;
+
+                if let Some(steps) = entry.sequence {
+                    let has_other_fields = entry.name.is_some()
+                        || entry.names.is_some()
+                        || entry.pick.is_some()
+                        || entry.duration.is_some()
+                        || entry.volume.is_some()
+                        || entry.fade_out.is_some()
+                        || entry.start.is_some()
+                        || entry.end.is_some()
+                        || entry.range.is_some()
+                        || entry.regex.is_some();
+                    if has_other_fields {
+                        return Err(de::Error::custom(
+                            "A sequence mapping entry can only have `sequence`, since each step sets its own values",
+                        ));
+                    }
+
+                    if !steps.iter().any(|step| matches!(step, SequenceStep::Sound { .. })) {
+                        return Err(de::Error::custom("A sequence must have at least one step with a `name`"));
+                    }
+
+                    return Ok(MappingEntry::Sequence(steps));
+                }
+
+                let pick = entry.pick.unwrap_or_default();
+
+                let names = match (entry.name, entry.names) {
+                    (Some(name), None) => vec![MappingCandidate::new(name)],
+                    (None, Some(names)) if !names.is_empty() => names,
+                    (Some(_), Some(_)) => return Err(de::Error::custom("A mapping entry can't have both `name` and `names`")),
+                    _ => {
+                        return Err(de::Error::custom(
+                            "A mapping entry must have a `name`, or a non-empty list of `names`",
+                        ));
+                    }
+                };
+
+                // Weights must be usable by `PickMode::Weighted`
+                if names
+                    .iter()
+                    .any(|candidate| !candidate.weight.is_finite() || candidate.weight < 0.0)
+                {
+                    return Err(de::Error::custom("Mapping weights must be non-negative numbers"));
+                }
+                if pick == PickMode::Weighted && names.iter().all(|candidate| candidate.weight <= 0.0) {
+                    return Err(de::Error::custom("At least one mapping weight must be above zero"));
+                }
+
+                Ok(MappingEntry::Detailed {
+                    names,
+                    pick,
+                    duration: entry.duration,
+                    volume: entry.volume,
+                    fade_out: entry.fade_out,
+                    start: entry.start,
+                    end: entry.end,
+                    range: entry.range,
+                    regex: entry.regex,
+                })
+            }
+        }
+
+        deserializer.deserialize_any(EntryVisitor)
+    }
+}
+
+impl Serialize for MappingEntry {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        // Only the fields which are set are written, using `name` instead of `names` where there is one name
+        #[derive(Serialize)]
+        struct DetailedEntry<'a> {
+            #[serde(skip_serializing_if = "Option::is_none")]
+            name: Option<&'a str>,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            names: Option<&'a [MappingCandidate]>,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            pick: Option<PickMode>,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            duration: Option<&'a str>,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            volume: Option<Volume>,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            fade_out: Option<&'a str>,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            start: Option<&'a str>,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            end: Option<&'a str>,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            range: Option<&'a str>,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            regex: Option<&'a str>,
+        }
+
+        #[derive(Serialize)]
+        struct SequenceEntry<'a> {
+            sequence: &'a [SequenceStep],
+        }
+
+        match self {
+            Self::Simple(name) => serializer.serialize_str(name),
+            Self::Sequence(steps) => SequenceEntry { sequence: steps }.serialize(serializer),
+            Self::Detailed {
+                names,
+                pick,
+                duration,
+                volume,
+                fade_out,
+                start,
+                end,
+                range,
+                regex,
+            } => {
+                let single_name = match names.as_slice() {
+                    [candidate] if (candidate.weight - DEFAULT_CANDIDATE_WEIGHT).abs() < f32::EPSILON => {
+                        Some(candidate.name.as_str())
+                    }
+                    _ => None,
+                };
+
+                DetailedEntry {
+                    name: single_name,
+                    names: single_name.is_none().then_some(names.as_slice()),
+                    pick: (*pick != PickMode::default()).then_some(*pick),
+                    duration: duration.as_deref(),
+                    volume: *volume,
+                    fade_out: fade_out.as_deref(),
+                    start: start.as_deref(),
+                    end: end.as_deref(),
+                    range: range.as_deref(),
+                    regex: regex.as_deref(),
+                }
+                .serialize(serializer)
+            }
         }
     }
 }
@@ -115,8 +1052,279 @@ impl std::fmt::Display for MappingEntry {
             f,
             "{}",
             match self {
-                Self::Simple(name) | Self::Detailed { name, duration: _ } => name,
+                Self::Simple(name) => name.clone(),
+                Self::Detailed { names, .. } => names
+                    .iter()
+                    .map(|candidate| candidate.name.as_str())
+                    .collect::<Vec<_>>()
+                    .join(", "),
+                Self::Sequence(steps) => steps.iter().map(ToString::to_string).collect::<Vec<_>>().join(", then "),
             }
         )
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_mapping(mapping_str: &str) -> Result<Mapping, toml::de::Error> {
+        toml::from_str(mapping_str)
+    }
+
+    #[test]
+    fn sequence_steps_are_read_in_order() -> Result<(), Box<dyn std::error::Error>> {
+        let mapping = parse_mapping(
+            r#"deploy-done = { sequence = [{ name = "complete" }, { gap = "80ms" }, { name = "message", duration = "50%", volume = 0.6, range = "..-10ms" }] }"#,
+        )?;
+
+        let Some(MappingEntry::Sequence(steps)) = mapping.get("deploy-done") else {
+            return Err(format!("'deploy-done' isn't a sequence: {mapping:?}").into());
+        };
+        assert_eq!(
+            steps,
+            &vec![
+                SequenceStep::Sound {
+                    name: String::from("complete"),
+                    duration: None,
+                    volume: None,
+                    fade_out: None,
+                    range: None,
+                },
+                SequenceStep::Gap {
+                    gap: String::from("80ms")
+                },
+                SequenceStep::Sound {
+                    name: String::from("message"),
+                    duration: Some(String::from("50%")),
+                    volume: Some(Volume::new(0.6)?),
+                    fade_out: None,
+                    range: Some(String::from("..-10ms")),
+                },
+            ]
+        );
+        assert_eq!(
+            mapping.get("deploy-done").map(ToString::to_string).as_deref(),
+            Some("complete, then 80ms gap, then message")
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn invalid_sequences_are_rejected() {
+        for (mapping_str, reason) in [
+            (r#"a = { sequence = [{ gap = "80ms" }] }"#, "no sound step"),
+            ("a = { sequence = [] }", "no steps"),
+            (
+                r#"a = { sequence = [{ name = "bell" }, { gap = "50%" }] }"#,
+                "a percentage gap",
+            ),
+            (
+                r#"a = { sequence = [{ name = "bell" }, { gap = "-80ms" }] }"#,
+                "a negative gap",
+            ),
+            (
+                r#"a = { sequence = [{ name = "bell" }, { gap = "80ms", volume = 0.5 }] }"#,
+                "a gap with a volume",
+            ),
+            (
+                r#"a = { sequence = [{ name = "bell", gap = "80ms" }] }"#,
+                "a step with a name and gap",
+            ),
+            (r#"a = { sequence = [{ duration = "1s" }] }"#, "a step with no name or gap"),
+            (r#"a = { sequence = [{ name = "@other" }] }"#, "an alias step"),
+            (
+                r#"a = { sequence = [{ name = "bell", duration = "loud" }] }"#,
+                "an invalid duration",
+            ),
+            (r#"a = { sequence = [{ name = "bell", range = "1s" }] }"#, "an invalid range"),
+            (r#"a = { sequence = [{ name = "bell", pitch = 2 }] }"#, "an unknown field"),
+            (
+                r#"a = { sequence = [{ name = "bell" }], volume = 0.5 }"#,
+                "a sequence with other fields",
+            ),
+        ] {
+            assert!(
+                parse_mapping(mapping_str).is_err(),
+                "A sequence with {reason} was accepted: {mapping_str}"
+            );
+        }
+    }
+
+    #[test]
+    fn weights_must_be_non_negative() -> Result<(), Box<dyn std::error::Error>> {
+        // A weight of zero stops a name from being picked, as long as another name can be
+        parse_mapping(r#"a = { names = [{ name = "bell", weight = 0 }, "message"], pick = "weighted" }"#)?;
+
+        let result = parse_mapping(r#"a = { names = [{ name = "bell", weight = -1 }, "message"], pick = "weighted" }"#);
+        assert!(
+            result
+                .as_ref()
+                .is_err_and(|e| e.message().contains("Mapping weights must be non-negative numbers")),
+            "{result:?}"
+        );
+        assert!(parse_mapping(r#"a = { names = [{ name = "bell", weight = 0 }], pick = "weighted" }"#).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn globs_match_whole_names() -> Result<(), Box<dyn std::error::Error>> {
+        assert!(is_glob("ci-*-failed"));
+        assert!(is_glob("dialog-?"));
+        assert!(!is_glob("dialog-error"));
+
+        let regex = Regex::new(&glob_to_regex("ci-*.failed?"))?;
+        assert!(regex.is_match("ci-build.failed!"));
+        assert!(regex.is_match("ci-.failedx"));
+        // Other characters are matched literally, and the glob must match the whole name
+        assert!(!regex.is_match("ci-buildxfailed!"));
+        assert!(!regex.is_match("ci-build.failed"));
+        assert!(!regex.is_match("my-ci-build.failed!"));
+
+        let captures = regex.captures("ci-lint.failed2").ok_or("No captures")?;
+        assert_eq!(captures.get(1).map(|capture| capture.as_str()), Some("lint"));
+        assert_eq!(captures.get(2).map(|capture| capture.as_str()), Some("2"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn rules_expand_capture_groups() -> Result<(), Box<dyn std::error::Error>> {
+        let mapping = parse_mapping(
+            r#"
+"ci-*-failed" = "build-$1-error"
+"ci-lint-failed" = "dialog-warning"
+"notify-?" = { name = "message-${1}x", volume = 0.5 }
+usb = { name = "device-${action}", regex = "^usb-(?P<action>added|removed)$" }
+"#,
+        )?;
+
+        assert_eq!(
+            mapping.find("ci-test-failed"),
+            Some(MappingEntry::Simple(String::from("build-test-error")))
+        );
+        // Exact keys are used before any rule
+        assert_eq!(
+            mapping.find("ci-lint-failed"),
+            Some(MappingEntry::Simple(String::from("dialog-warning")))
+        );
+        assert!(mapping.get_matching_rule("ci-lint-failed").is_none());
+
+        assert_eq!(
+            mapping.find("notify-3").map(|entry| entry.name()).as_deref(),
+            Some("message-3x")
+        );
+        assert!(matches!(
+            mapping.find("notify-3"),
+            Some(MappingEntry::Detailed { volume: Some(volume), .. }) if volume == Volume::new(0.5)?
+        ));
+        assert_eq!(
+            mapping.find("usb-added").map(|entry| entry.name()).as_deref(),
+            Some("device-added")
+        );
+
+        assert!(mapping.find("notify-10").is_none());
+        assert!(mapping.find("usb-changed").is_none());
+
+        Ok(())
+    }
+
+    #[test]
+    fn aliases_are_followed_to_their_entry() -> Result<(), Box<dyn std::error::Error>> {
+        let mut mapping = parse_mapping(
+            r#"
+error = "@failure"
+failure = "@dialog-error"
+dialog-error = "dialog-error-sound"
+unmapped = "@no-such-key"
+"#,
+        )?;
+        mapping.resolve_aliases()?;
+
+        assert_eq!(
+            mapping.find("error"),
+            Some(MappingEntry::Simple(String::from("dialog-error-sound")))
+        );
+        assert_eq!(
+            mapping.get_alias_chain("error").map(Vec::as_slice),
+            Some(&[String::from("error"), String::from("failure"), String::from("dialog-error")][..])
+        );
+        // An alias to a key which isn't mapped plays that key as a sound name
+        assert_eq!(
+            mapping.find("unmapped"),
+            Some(MappingEntry::Simple(String::from("no-such-key")))
+        );
+        assert!(mapping.get_alias_chain("dialog-error").is_none());
+
+        Ok(())
+    }
+
+    #[test]
+    fn alias_settings_override_the_entries_they_point_at() -> Result<(), Box<dyn std::error::Error>> {
+        let mut mapping = parse_mapping(
+            r#"
+quiet-error = { name = "@error", volume = 0.5 }
+error = { name = "@dialog-error", duration = "1s", volume = 1.2 }
+dialog-error = { name = "dialog-error", fade_out = "20ms", volume = 0.8 }
+"#,
+        )?;
+        mapping.resolve_aliases()?;
+
+        let Some(MappingEntry::Detailed {
+            names,
+            duration,
+            volume,
+            fade_out,
+            ..
+        }) = mapping.find("quiet-error")
+        else {
+            return Err(format!("'quiet-error' wasn't resolved: {mapping:?}").into());
+        };
+        assert_eq!(names, vec![MappingCandidate::new("dialog-error")]);
+        assert_eq!(volume, Some(Volume::new(0.5)?));
+        assert_eq!(duration.as_deref(), Some("1s"));
+        assert_eq!(fade_out.as_deref(), Some("20ms"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn alias_cycles_are_errors() -> Result<(), Box<dyn std::error::Error>> {
+        let mut mapping = parse_mapping(
+            r#"
+a = "@b"
+b = "@c"
+c = "@a"
+"#,
+        )?;
+        match mapping.resolve_aliases() {
+            Err(ThemerError::MappingAliasError { key, e }) => {
+                assert_eq!(key, "a");
+                assert!(e.contains("a --> b --> c --> a"), "{e}");
+            }
+            result => return Err(format!("The cycle wasn't reported: {result:?}").into()),
+        }
+
+        let mut mapping = parse_mapping(r#"self = "@self""#)?;
+        assert!(matches!(
+            mapping.resolve_aliases(),
+            Err(ThemerError::MappingAliasError { .. })
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn aliases_must_be_the_only_name() -> Result<(), Box<dyn std::error::Error>> {
+        let mut mapping = parse_mapping(r#"error = { names = ["@dialog-error", "bell"] }"#)?;
+
+        assert!(matches!(
+            mapping.resolve_aliases(),
+            Err(ThemerError::MappingAliasError { key, .. }) if key == "error"
+        ));
+
+        Ok(())
+    }
+}
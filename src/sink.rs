@@ -0,0 +1,512 @@
+use std::{
+    fs::{self, OpenOptions},
+    io::{BufWriter, Write},
+    os::unix::fs::OpenOptionsExt,
+    path::{Path, PathBuf},
+};
+
+use crate::{decode::SoundSpec, error::ThemerError};
+
+/// # Documentation
+/// An output which PCM samples can be written to
+pub trait AudioSink {
+    /// # Documentation
+    /// Write interleaved `f32` samples to the sink
+    ///
+    /// # Errors
+    /// Returns an error if the samples could not be written
+    fn write(&mut self, samples: &[f32], spec: SoundSpec) -> Result<(), ThemerError>;
+
+    /// # Documentation
+    /// Block until every sample which has been written has been output
+    ///
+    /// # Errors
+    /// Returns an error if the sink could not be flushed
+    fn finish(&mut self) -> Result<(), ThemerError>;
+}
+
+/// # Documentation
+/// A sink which discards every sample, only counting how many frames were written
+#[derive(Debug, Default, Clone)]
+pub struct NullSink {
+    pub frames_written: usize,
+}
+
+impl AudioSink for NullSink {
+    fn write(&mut self, samples: &[f32], spec: SoundSpec) -> Result<(), ThemerError> {
+        self.frames_written += samples.len() / usize::from(spec.channels.max(1));
+
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<(), ThemerError> {
+        Ok(())
+    }
+}
+
+/// # Documentation
+/// A sink which writes every sample to a 16-bit PCM WAV file when it is finished
+#[derive(Debug, Clone)]
+pub struct WavSink {
+    path: PathBuf,
+    samples: Vec<f32>,
+    spec: Option<SoundSpec>,
+    /// # Documentation
+    /// Whether the file must not already exist, and is only readable by the user, set by `new_private()`
+    private: bool,
+}
+
+impl WavSink {
+    pub fn new<P: AsRef<Path>>(path: P) -> Self {
+        Self {
+            path: path.as_ref().to_path_buf(),
+            samples: Vec::new(),
+            spec: None,
+            private: false,
+        }
+    }
+
+    /// # Documentation
+    /// Create a sink which fails instead of writing to a file which already exists (e.g: a symlink left in a shared
+    /// temporary directory), and which creates the file so only the user can read it
+    pub fn new_private<P: AsRef<Path>>(path: P) -> Self {
+        Self {
+            private: true,
+            ..Self::new(path)
+        }
+    }
+
+    /// # Documentation
+    /// Get the path of the WAV file which is written to
+    #[must_use]
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl AudioSink for WavSink {
+    fn write(&mut self, samples: &[f32], spec: SoundSpec) -> Result<(), ThemerError> {
+        // A WAV file can only have a single format
+        if self.spec.is_some_and(|current_spec| current_spec != spec) {
+            return Err(ThemerError::AudioSinkError(format!(
+                "WAV file '{}' can't change format after samples have been written",
+                self.path.display()
+            )));
+        }
+
+        self.spec = Some(spec);
+        self.samples.extend_from_slice(samples);
+
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<(), ThemerError> {
+        let spec = self.spec.unwrap_or(SoundSpec {
+            sample_rate: 44100,
+            channels: 2,
+        });
+
+        let data_len = u32::try_from(self.samples.len() * 2)
+            .map_err(|_| ThemerError::AudioSinkError(String::from("Too many samples for a WAV file")))?;
+
+        let mut open_options = OpenOptions::new();
+        open_options.write(true);
+        if self.private {
+            open_options.create_new(true).mode(0o600);
+        } else {
+            open_options.create(true).truncate(true);
+        }
+
+        let file = open_options
+            .open(&self.path)
+            .map_err(|e| ThemerError::FileReadWriteError(format!("'{}': {e}", self.path.display())))?;
+
+        // A partly written file is removed, so it can't be played
+        self.write_wav(BufWriter::new(file), spec, data_len).inspect_err(|_| {
+            let _ = fs::remove_file(&self.path);
+        })
+    }
+}
+
+impl WavSink {
+    /// # Documentation
+    /// Write the header and samples of the WAV file
+    ///
+    /// # Errors
+    /// Returns an error if the file could not be written
+    #[allow(clippy::cast_possible_wrap)]
+    fn write_wav<W: Write>(&self, mut writer: W, spec: SoundSpec, data_len: u32) -> Result<(), ThemerError> {
+        let block_align = spec.channels * 2;
+
+        // RIFF header
+        writer.write_all(b"RIFF")?;
+        writer.write_all(&(36 + data_len).to_le_bytes())?;
+        writer.write_all(b"WAVE")?;
+
+        // Format chunk (PCM)
+        writer.write_all(b"fmt ")?;
+        writer.write_all(&16_u32.to_le_bytes())?;
+        writer.write_all(&1_u16.to_le_bytes())?;
+        writer.write_all(&spec.channels.to_le_bytes())?;
+        writer.write_all(&spec.sample_rate.to_le_bytes())?;
+        writer.write_all(&(spec.sample_rate * u32::from(block_align)).to_le_bytes())?;
+        writer.write_all(&block_align.to_le_bytes())?;
+        writer.write_all(&16_u16.to_le_bytes())?;
+
+        // Data chunk, converting each sample to a 16-bit integer
+        writer.write_all(b"data")?;
+        writer.write_all(&data_len.to_le_bytes())?;
+        for sample in &self.samples {
+            writer.write_all(&((sample.clamp(-1.0, 1.0) * f32::from(i16::MAX)) as i16).to_le_bytes())?;
+        }
+
+        writer.flush()?;
+
+        Ok(())
+    }
+}
+
+#[cfg(feature = "native")]
+pub use device::DeviceSink;
+
+#[cfg(feature = "native")]
+mod device {
+    use std::{
+        collections::VecDeque,
+        sync::{Arc, Mutex},
+        thread,
+        time::Duration,
+    };
+
+    use cpal::{
+        Device, FromSample, SampleFormat, SizedSample, Stream, StreamConfig, SupportedStreamConfig,
+        traits::{DeviceTrait, HostTrait, StreamTrait},
+    };
+
+    use super::AudioSink;
+    use crate::{decode::SoundSpec, error::ThemerError, sound::get_stop_count};
+
+    // How often `finish()` checks whether the queued samples have been played
+    const DRAIN_POLL_INTERVAL: Duration = Duration::from_millis(5);
+    // How long the device may take to output its buffer after the last samples have been consumed
+    const DEVICE_LATENCY: Duration = Duration::from_millis(50);
+    // The sample formats which the output stream can convert `f32` samples to
+    const SUPPORTED_SAMPLE_FORMATS: [SampleFormat; 8] = [
+        SampleFormat::F32,
+        SampleFormat::F64,
+        SampleFormat::I8,
+        SampleFormat::I16,
+        SampleFormat::I32,
+        SampleFormat::U8,
+        SampleFormat::U16,
+        SampleFormat::U32,
+    ];
+
+    // An output stream, with the format of the samples written to the sink and the format the device plays
+    struct OpenStream {
+        _stream: Stream,
+        spec: SoundSpec,
+        output_spec: SoundSpec,
+    }
+
+    /// # Documentation
+    /// A sink which plays samples through the default output device, converting them to a format the device supports
+    pub struct DeviceSink {
+        stream: Option<OpenStream>,
+        queue: Arc<Mutex<VecDeque<f32>>>,
+        // The value of `get_stop_count()` when the sink was created, so the sink stops if `stop_sounds()` is called
+        stop_count: usize,
+    }
+
+    impl Default for DeviceSink {
+        fn default() -> Self {
+            Self {
+                stream: None,
+                queue: Arc::default(),
+                stop_count: get_stop_count(),
+            }
+        }
+    }
+
+    impl DeviceSink {
+        #[must_use]
+        pub fn new() -> Self {
+            Self::default()
+        }
+
+        /// # Errors
+        /// Returns an error if there is no default output device
+        /// Returns an error if `choose_output_config()` fails
+        /// Returns an error if an output stream could not be created for the chosen config
+        fn open_stream(&self, spec: SoundSpec) -> Result<OpenStream, ThemerError> {
+            let device = cpal::default_host()
+                .default_output_device()
+                .ok_or_else(|| ThemerError::AudioSinkError(String::from("No default output device found")))?;
+
+            let config = choose_output_config(&device, spec)?;
+            let stream_config = config.config();
+
+            let stream = match config.sample_format() {
+                SampleFormat::F32 => self.build_stream::<f32>(&device, &stream_config),
+                SampleFormat::F64 => self.build_stream::<f64>(&device, &stream_config),
+                SampleFormat::I8 => self.build_stream::<i8>(&device, &stream_config),
+                SampleFormat::I16 => self.build_stream::<i16>(&device, &stream_config),
+                SampleFormat::I32 => self.build_stream::<i32>(&device, &stream_config),
+                SampleFormat::U8 => self.build_stream::<u8>(&device, &stream_config),
+                SampleFormat::U16 => self.build_stream::<u16>(&device, &stream_config),
+                SampleFormat::U32 => self.build_stream::<u32>(&device, &stream_config),
+                sample_format => Err(ThemerError::AudioSinkError(format!(
+                    "The output device uses unsupported {sample_format} samples"
+                ))),
+            }?;
+
+            stream.play().map_err(|e| ThemerError::AudioSinkError(e.to_string()))?;
+
+            Ok(OpenStream {
+                _stream: stream,
+                spec,
+                output_spec: SoundSpec {
+                    sample_rate: stream_config.sample_rate,
+                    channels: stream_config.channels,
+                },
+            })
+        }
+
+        /// # Errors
+        /// Returns an error if the device couldn't create a stream of `T` samples for `config`
+        fn build_stream<T: SizedSample + FromSample<f32>>(
+            &self,
+            device: &Device,
+            config: &StreamConfig,
+        ) -> Result<Stream, ThemerError> {
+            let queue = Arc::clone(&self.queue);
+
+            device
+                .build_output_stream(
+                    config,
+                    move |data: &mut [T], _| {
+                        // Output silence if the queue can't be locked or has run out of samples
+                        let mut queue = queue.lock().ok();
+                        for sample in data.iter_mut() {
+                            *sample = T::from_sample(queue.as_mut().and_then(|queue| queue.pop_front()).unwrap_or(0.0));
+                        }
+                    },
+                    |e| eprintln!("Error: {}", ThemerError::AudioSinkError(e.to_string())),
+                    None,
+                )
+                .map_err(|e| ThemerError::AudioSinkError(e.to_string()))
+        }
+    }
+
+    impl AudioSink for DeviceSink {
+        fn write(&mut self, samples: &[f32], spec: SoundSpec) -> Result<(), ThemerError> {
+            // Re-open the stream if the format has changed
+            if self.stream.as_ref().is_none_or(|stream| stream.spec != spec) {
+                self.finish()?;
+                self.stream = Some(self.open_stream(spec)?);
+            }
+
+            let output_spec = self.stream.as_ref().map_or(spec, |stream| stream.output_spec);
+            let samples = convert_samples(samples, spec, output_spec);
+
+            self.queue
+                .lock()
+                .map_err(|e| ThemerError::MutexLockError(e.to_string()))?
+                .extend(samples);
+
+            Ok(())
+        }
+
+        fn finish(&mut self) -> Result<(), ThemerError> {
+            // Wait for the output callback to consume every queued sample, unless the sound has been stopped
+            loop {
+                let mut queue = self.queue.lock().map_err(|e| ThemerError::MutexLockError(e.to_string()))?;
+
+                if get_stop_count() != self.stop_count {
+                    queue.clear();
+                }
+
+                if queue.is_empty() {
+                    break;
+                }
+
+                drop(queue);
+                thread::sleep(DRAIN_POLL_INTERVAL);
+            }
+
+            // Let the device output its remaining buffer before the stream is closed
+            if self.stream.take().is_some() {
+                thread::sleep(DEVICE_LATENCY);
+            }
+
+            Ok(())
+        }
+    }
+
+    /// # Documentation
+    /// Choose the config which the device plays `spec` with, from the configs it supports. Configs with the same channel
+    /// count are preferred, then configs which support the sample rate (Otherwise the closest rate is used), then configs
+    /// with `f32` samples. The device's default config is used if it doesn't list the configs it supports
+    ///
+    /// # Errors
+    /// Returns an error if the device has no supported configs, and no default config
+    fn choose_output_config(device: &Device, spec: SoundSpec) -> Result<SupportedStreamConfig, ThemerError> {
+        let supported_config = device
+            .supported_output_configs()
+            .into_iter()
+            .flatten()
+            .filter(|range| SUPPORTED_SAMPLE_FORMATS.contains(&range.sample_format()))
+            .max_by_key(|range| {
+                (
+                    range.channels() == spec.channels,
+                    (range.min_sample_rate()..=range.max_sample_rate()).contains(&spec.sample_rate),
+                    range.channels() >= spec.channels,
+                    range.sample_format() == SampleFormat::F32,
+                )
+            })
+            .and_then(|range| {
+                let sample_rate = spec.sample_rate.clamp(range.min_sample_rate(), range.max_sample_rate());
+                range.try_with_sample_rate(sample_rate)
+            });
+
+        supported_config.map_or_else(
+            || {
+                device
+                    .default_output_config()
+                    .map_err(|e| ThemerError::AudioSinkError(e.to_string()))
+            },
+            Ok,
+        )
+    }
+
+    /// # Documentation
+    /// Convert interleaved samples from `spec` to the channel count and sample rate of `output_spec`
+    fn convert_samples(samples: &[f32], spec: SoundSpec, output_spec: SoundSpec) -> Vec<f32> {
+        let samples = remap_channels(samples, spec.channels, output_spec.channels);
+
+        resample(&samples, output_spec.channels, spec.sample_rate, output_spec.sample_rate)
+    }
+
+    /// # Documentation
+    /// Change the number of interleaved channels. Mono is copied to every channel, and every channel is mixed together
+    /// for mono, otherwise each channel is kept in its position, where any extra output channels are silent
+    #[allow(clippy::cast_precision_loss)]
+    fn remap_channels(samples: &[f32], channels: u16, output_channels: u16) -> Vec<f32> {
+        if channels == output_channels || channels == 0 || output_channels == 0 {
+            return samples.to_vec();
+        }
+
+        let output_channels = usize::from(output_channels);
+        samples
+            .chunks_exact(usize::from(channels))
+            .flat_map(|frame| {
+                (0..output_channels).map(move |channel| match (frame.len(), output_channels) {
+                    (1, _) => frame[0],
+                    (_, 1) => frame.iter().sum::<f32>() / frame.len() as f32,
+                    _ => frame.get(channel).copied().unwrap_or(0.0),
+                })
+            })
+            .collect()
+    }
+
+    /// # Documentation
+    /// Change the sample rate of interleaved samples, using linear interpolation between neighbouring frames
+    #[allow(clippy::cast_precision_loss, clippy::cast_sign_loss)]
+    fn resample(samples: &[f32], channels: u16, sample_rate: u32, output_sample_rate: u32) -> Vec<f32> {
+        let channels = usize::from(channels.max(1));
+        let frames = samples.len() / channels;
+        if sample_rate == output_sample_rate || sample_rate == 0 || output_sample_rate == 0 || frames == 0 {
+            return samples.to_vec();
+        }
+
+        let output_frames = (frames as u64 * u64::from(output_sample_rate) / u64::from(sample_rate)) as usize;
+        let step = f64::from(sample_rate) / f64::from(output_sample_rate);
+
+        let mut output = Vec::with_capacity(output_frames * channels);
+        for output_frame in 0..output_frames {
+            let position = output_frame as f64 * step;
+            let frame = (position as usize).min(frames - 1);
+            let next_frame = (frame + 1).min(frames - 1);
+            let fraction = (position - frame as f64) as f32;
+
+            for channel in 0..channels {
+                let sample = samples[frame * channels + channel];
+                let next_sample = samples[next_frame * channels + channel];
+                output.push((next_sample - sample).mul_add(fraction, sample));
+            }
+        }
+
+        output
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn mono_is_copied_to_every_channel() {
+            assert_eq!(remap_channels(&[0.1, 0.2], 1, 2), vec![0.1, 0.1, 0.2, 0.2]);
+        }
+
+        #[test]
+        fn channels_are_mixed_for_mono() {
+            assert_eq!(remap_channels(&[0.2, 0.4, -1.0, 1.0], 2, 1), vec![0.3, 0.0]);
+        }
+
+        #[test]
+        fn channels_keep_their_positions() {
+            // Extra output channels are silent, and extra input channels are dropped
+            assert_eq!(
+                remap_channels(&[0.1, 0.2, 0.3, 0.4], 2, 4),
+                vec![0.1, 0.2, 0.0, 0.0, 0.3, 0.4, 0.0, 0.0]
+            );
+            assert_eq!(
+                remap_channels(&[0.1, 0.2, 0.3, 0.4, 0.5, 0.6], 3, 2),
+                vec![0.1, 0.2, 0.4, 0.5]
+            );
+            assert_eq!(remap_channels(&[0.1, 0.2], 2, 2), vec![0.1, 0.2]);
+        }
+
+        #[test]
+        fn resampling_interpolates_between_frames() {
+            // Doubling the rate adds a frame halfway between each pair of frames
+            assert_eq!(
+                resample(&[0.0, 1.0, 0.0, -1.0], 1, 22050, 44100),
+                vec![0.0, 0.5, 1.0, 0.5, 0.0, -0.5, -1.0, -1.0]
+            );
+            // Each channel is interpolated on its own
+            assert_eq!(
+                resample(&[0.0, 1.0, 1.0, 0.0], 2, 1, 2),
+                vec![0.0, 1.0, 0.5, 0.5, 1.0, 0.0, 1.0, 0.0]
+            );
+            // Halving the rate keeps every other frame
+            assert_eq!(resample(&[0.0, 0.1, 0.2, 0.3], 1, 48000, 24000), vec![0.0, 0.2]);
+            assert_eq!(resample(&[0.1, 0.2], 1, 44100, 44100), vec![0.1, 0.2]);
+        }
+
+        #[test]
+        fn resampling_keeps_the_length_in_time() {
+            let samples = vec![0.0; 44100 * 2];
+            let output = resample(&samples, 2, 44100, 48000);
+
+            assert_eq!(output.len(), 48000 * 2);
+        }
+
+        #[test]
+        fn samples_are_remapped_then_resampled() {
+            let spec = SoundSpec {
+                sample_rate: 1,
+                channels: 1,
+            };
+            let output_spec = SoundSpec {
+                sample_rate: 2,
+                channels: 2,
+            };
+
+            assert_eq!(
+                convert_samples(&[0.0, 1.0], spec, output_spec),
+                vec![0.0, 0.0, 0.5, 0.5, 1.0, 1.0, 1.0, 1.0]
+            );
+        }
+    }
+}
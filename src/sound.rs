@@ -1,21 +1,63 @@
-use std::{fs::File, path::Path, process::Child, str::FromStr, thread, time::Duration};
-
-use symphonia::{
-    core::{
-        io::{MediaSourceStream, MediaSourceStreamOptions},
-        meta::MetadataOptions,
-        probe::Hint,
-    },
-    default::get_probe,
+use std::{
+    collections::BTreeSet,
+    ffi::OsStr,
+    fmt, fs, iter,
+    os::unix::fs::PermissionsExt,
+    path::{Path, PathBuf},
+    process::Child,
+    str::FromStr,
+    sync::atomic::{AtomicUsize, Ordering},
+    thread,
+    time::{Duration, Instant},
 };
 
+use rand::prelude::*;
+#[cfg(not(feature = "native"))]
+use strum::IntoEnumIterator;
+use strum::{Display, EnumIter};
+
+#[cfg(feature = "native")]
+use crate::sink::DeviceSink;
 use crate::{
-    duration::{PlaybackDuration, playback_duration_to_duration},
+    cache::{CachedDirectory, CachedSound, SoundInfo, get_modified_time, lock_sound_cache},
+    config::{PlayerBackendKind, PlayerConfig, get_toml_config},
+    decode::{DecodedSound, decode_sound_file, probe_sound_file},
+    duration::{PlaybackDuration, PlaybackRange, playback_range_to_durations},
     error::ThemerError,
-    mapping::{MappingEntry, MappingKey},
-    theme::{get_selected_theme, get_selected_theme_paths},
+    locale::{get_locale, get_locale_variants},
+    mapping::{MappingEntry, PickMode, SequenceStep},
+    sink::{AudioSink, WavSink},
+    state::next_round_robin_index,
+    theme::{Theme, get_selected_theme, get_selected_theme_lookup_paths},
+    volume::Volume,
+    xdg::get_private_runtime_dir,
 };
 
+// The paplay volume which leaves the sound unchanged
+const PAPLAY_VOLUME_NORM: f32 = 65536.0;
+// How long a player is given to exit after SIGTERM, before it is sent SIGKILL
+const STOP_GRACE_PERIOD: Duration = Duration::from_millis(100);
+const STOP_POLL_INTERVAL: Duration = Duration::from_millis(5);
+
+static TEMP_FILE_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+// Increased by `stop_sounds()`, so every sound which started playing before it changed is stopped
+static STOP_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+/// # Documentation
+/// Stop every sound which is currently being played by this process (e.g: by the daemon)
+pub fn stop_sounds() {
+    STOP_COUNTER.fetch_add(1, Ordering::SeqCst);
+}
+
+/// # Documentation
+/// Get the number of times `stop_sounds()` has been called, so a player can check whether it has been called since it
+/// started playing
+#[must_use]
+pub fn get_stop_count() -> usize {
+    STOP_COUNTER.load(Ordering::SeqCst)
+}
+
 /// # Errors
 /// Returns an error if the command for requested value cannot be spawned
 /// Returns an error if values in the output of the command cannot found
@@ -32,41 +74,588 @@ pub fn spawn<S: AsRef<str>>(name: S, args: &[S]) -> Result<Child, ThemerError> {
         })
 }
 
+/// # Documentation
+/// Values set when requesting a sound (e.g: from the CLI), which take priority over the values in the mapping
+#[derive(Debug, Clone, Default)]
+pub struct PlaybackOverrides {
+    pub duration: Option<PlaybackDuration>,
+    pub volume: Option<Volume>,
+    pub fade_out: Option<PlaybackDuration>,
+    pub range: PlaybackRange,
+}
+
+impl PlaybackOverrides {
+    /// # Documentation
+    /// Use the start and end of the mapped range where the overriding range doesn't set them. The mapped end is ignored if
+    /// a duration is overridden, so the overriding duration is played instead of stopping at the mapped end
+    #[must_use]
+    pub fn merge_range(&self, mapped_range: PlaybackRange) -> PlaybackRange {
+        let mapped_range = if self.duration.is_some() {
+            PlaybackRange {
+                end: None,
+                ..mapped_range
+            }
+        } else {
+            mapped_range
+        };
+
+        self.range.clone().or(mapped_range)
+    }
+}
+
+/// # Documentation
+/// A sound file which has been found for a sound name, along with the options it is played with
+#[derive(Debug, Clone, PartialEq)]
+pub struct ResolvedSound {
+    pub path: String,
+    pub options: PlaybackOptions,
+}
+
+/// # Documentation
+/// A sound name requested along with its own duration, written as `name@duration` (e.g: `complete@250ms`, `message@50%`)
+#[derive(Debug, Clone)]
+pub struct SoundItem {
+    pub sound_name: String,
+    pub duration: Option<PlaybackDuration>,
+}
+
+impl FromStr for SoundItem {
+    type Err = ThemerError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (sound_name, duration) = match s.rsplit_once('@') {
+            Some((sound_name, duration)) => (sound_name, Some(PlaybackDuration::from_str(duration)?)),
+            None => (s, None),
+        };
+
+        if sound_name.trim().is_empty() {
+            return Err(ThemerError::SoundItemFromStrError {
+                from: s.to_string(),
+                e: String::from("A sound name must be given before the '@'"),
+            });
+        }
+
+        Ok(Self {
+            sound_name: sound_name.trim().to_string(),
+            duration,
+        })
+    }
+}
+
+impl fmt::Display for SoundItem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Written in the same format as it is read, so it can be passed to `from_str()` again
+        match &self.duration {
+            Some(duration) => write!(f, "{}@{duration}", self.sound_name),
+            None => write!(f, "{}", self.sound_name),
+        }
+    }
+}
+
+/// # Documentation
+/// A part of a sequence of sounds, which is either a sound to play or silence to wait through
+#[derive(Debug, Clone, PartialEq)]
+pub enum PlaybackStep {
+    Sound(ResolvedSound),
+    Gap(Duration),
+}
+
+/// # Documentation
+/// Play each step in order, blocking until they have all finished. Any steps after `stop_sounds()` is called are skipped
+///
 /// # Errors
-/// Returns an error if `playback_duration_to_duration()` fails
-/// Returns an error if `spawn()` fails to execute command with arguments
-pub fn play_sound<S: AsRef<str> + Clone>(sound_name: S, duration: Option<PlaybackDuration>) -> Result<(), ThemerError> {
+/// Returns an error if any of the sounds could not be played using `play_resolved_sound()`
+pub fn play_playback_steps(steps: &[PlaybackStep]) -> Result<(), ThemerError> {
+    let stop_count = get_stop_count();
+    for step in steps {
+        if get_stop_count() != stop_count {
+            break;
+        }
+
+        match step {
+            PlaybackStep::Sound(sound) => play_resolved_sound(sound)?,
+            PlaybackStep::Gap(gap) => thread::sleep(*gap),
+        }
+    }
+
+    Ok(())
+}
+
+/// # Documentation
+/// Play every sequence of steps at the same time, blocking until they have all finished
+///
+/// # Errors
+/// Returns an error if any of the sequences could not be played using `play_playback_steps()`
+pub fn play_parallel_playback_steps(sequences: &[Vec<PlaybackStep>]) -> Result<(), ThemerError> {
+    thread::scope(|scope| {
+        let mut handles = Vec::new();
+        for steps in sequences {
+            handles.push(scope.spawn(|| play_playback_steps(steps)));
+        }
+
+        // The scope waits for every sequence to finish, even after one of them fails
+        handles.into_iter().try_for_each(|handle| {
+            handle
+                .join()
+                .unwrap_or_else(|_| Err(ThemerError::AudioSinkError(String::from("Sound thread panicked"))))
+        })
+    })
+}
+
+/// # Errors
+/// Returns an error if `resolve_sound_steps()` fails
+/// Returns an error if `play_playback_steps()` fails
+pub fn play_sound<S: AsRef<str> + Clone>(sound_name: S, overrides: &PlaybackOverrides) -> Result<(), ThemerError> {
+    play_playback_steps(&resolve_sound_steps(sound_name, overrides)?)
+}
+
+/// # Documentation
+/// Find the steps which are played for `sound_name`, which is a single sound unless it is mapped to a sequence.
+/// The values set by each step of a sequence take priority over the overrides, except its volume, which is multiplied
+///
+/// # Errors
+/// Returns an error if `get_selected_theme()` fails
+/// Returns an error if a step's values could not be parsed
+/// Returns an error if any of the sounds could not be resolved using `resolve_sound()`
+pub fn resolve_sound_steps<S: AsRef<str> + Clone>(
+    sound_name: S,
+    overrides: &PlaybackOverrides,
+) -> Result<Vec<PlaybackStep>, ThemerError> {
+    let theme = get_selected_theme()?;
+
+    let Some(MappingEntry::Sequence(steps)) = get_mapping_entry(&theme, sound_name.as_ref()) else {
+        return Ok(vec![PlaybackStep::Sound(resolve_sound(sound_name, overrides)?)]);
+    };
+
+    steps
+        .iter()
+        .map(|step| match step {
+            SequenceStep::Sound {
+                name,
+                duration,
+                volume,
+                fade_out,
+                range,
+            } => {
+                let step_overrides = PlaybackOverrides {
+                    duration: duration
+                        .as_deref()
+                        .map(PlaybackDuration::from_str)
+                        .transpose()?
+                        .or_else(|| overrides.duration.clone()),
+                    volume: Some(Volume::product([*volume, overrides.volume].into_iter().flatten())),
+                    fade_out: fade_out
+                        .as_deref()
+                        .map(PlaybackDuration::from_str)
+                        .transpose()?
+                        .or_else(|| overrides.fade_out.clone()),
+                    range: range
+                        .as_deref()
+                        .map(PlaybackRange::from_str)
+                        .transpose()?
+                        .unwrap_or_else(|| overrides.range.clone()),
+                };
+
+                Ok(PlaybackStep::Sound(resolve_sound(name, &step_overrides)?))
+            }
+            SequenceStep::Gap { gap } => Ok(PlaybackStep::Gap(PlaybackDuration::from_str(gap)?.to_gap()?)),
+        })
+        .collect()
+}
+
+/// # Errors
+/// Returns an error if `get_toml_config()` fails
+/// Returns an error if `get_player_backend()` fails
+/// Returns an error if the sound could not be played by the `PlayerBackend`
+pub fn play_resolved_sound(sound: &ResolvedSound) -> Result<(), ThemerError> {
+    let player = get_player_backend(&get_toml_config()?.player)?;
+
+    player.play(&sound.path, sound.options)
+}
+
+/// # Documentation
+/// Find the sound file for `sound_name`, and combine the overrides with the config and mapping to get its `PlaybackOptions`
+///
+/// # Errors
+/// Returns an error if `get_sound_from_name()` fails
+/// Returns an error if `get_toml_config()` or `get_selected_theme()` fails
+/// Returns an error if `playback_range_to_durations()` fails
+pub fn resolve_sound<S: AsRef<str> + Clone>(sound_name: S, overrides: &PlaybackOverrides) -> Result<ResolvedSound, ThemerError> {
     let sound_path_str = get_sound_from_name(sound_name.clone())?;
 
-    // Map the PlaybackDuration to a true Duration
-    let duration = if let Some(duration) = duration {
-        Some(playback_duration_to_duration(&duration, sound_name)?)
-    } else {
-        // Duration not set, so check if there is a mapping for this name that includes duration information
-        let theme = get_selected_theme()?;
-
-        // Find if there is a corresponding mapping with the duration set for this sound_name
-        let duration = MappingKey::from_str(sound_name.as_ref())
-            .ok()
-            .and_then(|key| theme.mapping.get(&key).cloned()) // Map to associated value in Mapping
-            .and_then(|mapping_entry| mapping_entry.duration()); // Map to the duration, if possible
-
-        // If there was a mapping, convert to PlaybackDuration, then to a true Duration
-        if let Some(duration) = duration {
-            let playback_duration = PlaybackDuration::from_str(duration.as_str())?;
-            Some(playback_duration_to_duration(&playback_duration, sound_name)?)
+    let config = get_toml_config()?;
+    let theme = get_selected_theme()?;
+    // A sequence is played by `resolve_sound_steps()`, so a step with the same name as its key is played as it is
+    let mapping_entry = get_mapping_entry(&theme, sound_name.as_ref()).filter(|mapping_entry| !mapping_entry.is_sequence());
+
+    // The range and duration in the mapping are used where they aren't overridden
+    let range = overrides.merge_range(match &mapping_entry {
+        Some(mapping_entry) => mapping_entry.range()?,
+        None => PlaybackRange::default(),
+    });
+    let playback_duration = match (&overrides.duration, mapping_entry.as_ref().and_then(MappingEntry::duration)) {
+        (Some(playback_duration), _) => Some(playback_duration.clone()),
+        (None, Some(mapped_duration)) => Some(PlaybackDuration::from_str(mapped_duration.as_str())?),
+        (None, None) => None,
+    };
+
+    // Map the PlaybackRange and PlaybackDuration to true Durations
+    let (start, duration) = playback_range_to_durations(&range, playback_duration.as_ref(), &sound_path_str)?;
+
+    // A sound which is cut short always fades out, using the global fade-out if no other is set
+    let fade_out = match resolve_duration(
+        overrides.fade_out.as_ref(),
+        mapping_entry.as_ref().and_then(MappingEntry::fade_out),
+        &sound_path_str,
+    )? {
+        Some(fade_out) => Some(fade_out),
+        None if duration.is_some() => resolve_duration(None, Some(config.fade_out.clone()), &sound_path_str)?,
+        None => None,
+    };
+
+    // The global, theme, mapping, and requested volumes are multiplied together
+    let volume = Volume::product(
+        [
+            Some(config.volume),
+            Some(theme.volume),
+            mapping_entry.as_ref().and_then(MappingEntry::volume),
+            overrides.volume,
+        ]
+        .into_iter()
+        .flatten(),
+    );
+
+    Ok(ResolvedSound {
+        path: sound_path_str,
+        options: PlaybackOptions {
+            start,
+            duration,
+            volume: volume.value(),
+            fade_out,
+        },
+    })
+}
+
+/// # Documentation
+/// Convert the overriding `PlaybackDuration`, or otherwise the duration string from the mapping, to a true `Duration`
+///
+/// # Errors
+/// Returns an error if the mapped duration could not be parsed by `PlaybackDuration::from_str()`
+/// Returns an error if `get_sound_duration_from_path()` fails
+/// Returns an error if `PlaybackDuration::to_duration()` fails
+fn resolve_duration<S: AsRef<str>>(
+    playback_duration: Option<&PlaybackDuration>,
+    mapped_duration: Option<String>,
+    sound_path_str: S,
+) -> Result<Option<Duration>, ThemerError> {
+    let playback_duration = match (playback_duration, mapped_duration) {
+        (Some(playback_duration), _) => playback_duration.clone(),
+        (None, Some(mapped_duration)) => PlaybackDuration::from_str(mapped_duration.as_str())?,
+        (None, None) => return Ok(None),
+    };
+
+    Ok(Some(
+        playback_duration.to_duration(get_sound_duration_from_path(sound_path_str)?)?,
+    ))
+}
+
+/// # Documentation
+/// Get the entry in the theme's mapping for `sound_name`, if there is one
+#[must_use]
+pub fn get_mapping_entry<S: AsRef<str>>(theme: &Theme, sound_name: S) -> Option<MappingEntry> {
+    theme.mapping.find(sound_name.as_ref()) // Map to associated value in Mapping
+}
+
+/// # Documentation
+/// The parameters which are given to a `PlayerBackend` when it plays a sound
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PlaybackOptions {
+    /// # Documentation
+    /// Start playing the sound from this offset, otherwise play from the start
+    pub start: Option<Duration>,
+    /// # Documentation
+    /// Stop the sound after it has played for this duration, otherwise play the rest of the sound
+    pub duration: Option<Duration>,
+    /// # Documentation
+    /// The linear volume multiplier, where 1.0 is the volume of the sound file
+    pub volume: f32,
+    /// # Documentation
+    /// Fade the end of the played sound out over this duration
+    pub fade_out: Option<Duration>,
+}
+
+impl PlaybackOptions {
+    /// # Documentation
+    /// Whether the sound must be trimmed or faded before it is played
+    #[must_use]
+    pub const fn needs_render(&self) -> bool {
+        self.start.is_some() || self.duration.is_some() || self.fade_out.is_some()
+    }
+}
+
+impl Default for PlaybackOptions {
+    fn default() -> Self {
+        Self {
+            start: None,
+            duration: None,
+            volume: 1.0,
+            fade_out: None,
+        }
+    }
+}
+
+/// # Documentation
+/// A method of playing a sound file
+pub trait PlayerBackend {
+    /// # Documentation
+    /// The name of the backend, as used in the `[player]` section of the config
+    fn name(&self) -> String;
+
+    /// # Documentation
+    /// Play the sound file at `sound_path_str`, blocking until it has finished
+    ///
+    /// # Errors
+    /// Returns an error if the sound could not be played
+    fn play(&self, sound_path_str: &str, options: PlaybackOptions) -> Result<(), ThemerError>;
+}
+
+/// # Documentation
+/// The external players which are supported without a custom command, in the order they are detected
+#[derive(Debug, Clone, Copy, PartialEq, Eq, EnumIter, Display)]
+#[strum(serialize_all = "kebab-case")]
+pub enum BuiltinPlayer {
+    PwPlay,
+    Paplay,
+    Aplay,
+    Ffplay,
+}
+
+impl BuiltinPlayer {
+    /// # Documentation
+    /// Whether the player can change the volume of the sound it plays
+    #[must_use]
+    pub const fn sets_volume(self) -> bool {
+        !matches!(self, Self::Aplay)
+    }
+
+    /// # Documentation
+    /// Get the arguments to play a sound with this player
+    #[allow(clippy::cast_sign_loss)]
+    fn get_args(self, sound_path_str: &str, options: PlaybackOptions) -> Vec<String> {
+        match self {
+            Self::PwPlay => vec![
+                String::from("--volume"),
+                options.volume.to_string(),
+                sound_path_str.to_string(),
+            ],
+            // paplay uses a linear volume from 0 to 65536
+            Self::Paplay => vec![
+                format!("--volume={}", (options.volume * PAPLAY_VOLUME_NORM).round() as u32),
+                sound_path_str.to_string(),
+            ],
+            // aplay has no volume, so it is given a copy of the sound which is already at the volume
+            Self::Aplay => vec![String::from("-q"), sound_path_str.to_string()],
+            // ffplay uses a volume from 0 to 100, and can stop after a duration
+            Self::Ffplay => {
+                let mut args = ["-nodisp", "-autoexit", "-loglevel", "quiet", "-volume"]
+                    .into_iter()
+                    .map(ToString::to_string)
+                    .chain(iter::once(((options.volume * 100.0).round() as u32).to_string()))
+                    .collect::<Vec<_>>();
+
+                if let Some(duration) = options.duration {
+                    args.extend([String::from("-t"), duration.as_secs_f64().to_string()]);
+                }
+
+                args.push(sound_path_str.to_string());
+
+                args
+            }
+        }
+    }
+}
+
+impl PlayerBackend for BuiltinPlayer {
+    fn name(&self) -> String {
+        self.to_string()
+    }
+
+    fn play(&self, sound_path_str: &str, options: PlaybackOptions) -> Result<(), ThemerError> {
+        run_external_player(sound_path_str, options, self.sets_volume(), |path_str, options| {
+            Ok((self.name(), self.get_args(path_str, options)))
+        })
+    }
+}
+
+/// # Documentation
+/// A player which runs a custom command, where `{path}`, `{volume}`, `{volume_percent}`, and `{duration}` are replaced in
+/// each argument
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CommandPlayer {
+    pub template: String,
+}
+
+impl CommandPlayer {
+    pub fn new<S: AsRef<str>>(template: S) -> Self {
+        Self {
+            template: template.as_ref().to_string(),
+        }
+    }
+
+    /// # Documentation
+    /// Whether the command is given the volume, otherwise it is given a copy of the sound which is already at the volume
+    #[must_use]
+    pub fn sets_volume(&self) -> bool {
+        self.template.contains("{volume}") || self.template.contains("{volume_percent}")
+    }
+}
+
+impl PlayerBackend for CommandPlayer {
+    fn name(&self) -> String {
+        String::from("command")
+    }
+
+    fn play(&self, sound_path_str: &str, options: PlaybackOptions) -> Result<(), ThemerError> {
+        let template_args = split_command_template(&self.template)?;
+
+        run_external_player(sound_path_str, options, self.sets_volume(), |path_str, options| {
+            fill_command_template(&template_args, path_str, options)
+        })
+    }
+}
+
+/// # Documentation
+/// Replace `{path}`, `{volume}` (A linear multiplier), `{volume_percent}`, and `{duration}` (In seconds, or empty) in each
+/// argument of a split command template, giving the program and its arguments
+///
+/// # Errors
+/// Returns an error if the template has no arguments
+#[allow(clippy::cast_sign_loss)]
+fn fill_command_template(
+    template_args: &[String],
+    path_str: &str,
+    options: PlaybackOptions,
+) -> Result<(String, Vec<String>), ThemerError> {
+    let volume_str = options.volume.to_string();
+    let volume_percent_str = ((options.volume * 100.0).round() as u32).to_string();
+    let duration_str = options
+        .duration
+        .map(|duration| duration.as_secs_f64().to_string())
+        .unwrap_or_default();
+
+    // The template is split before substituting, so the path stays as one argument even if it contains spaces
+    let mut args = template_args.iter().map(|arg| {
+        arg.replace("{path}", path_str)
+            .replace("{volume_percent}", &volume_percent_str)
+            .replace("{volume}", &volume_str)
+            .replace("{duration}", &duration_str)
+    });
+
+    let program = args
+        .next()
+        .ok_or_else(|| ThemerError::PlayerConfigError(String::from("Player command is empty")))?;
+
+    Ok((program, args.collect()))
+}
+
+/// # Documentation
+/// A player which decodes the sound in-process and plays it through the default output device
+#[cfg(feature = "native")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NativePlayer;
+
+#[cfg(feature = "native")]
+impl PlayerBackend for NativePlayer {
+    fn name(&self) -> String {
+        String::from("native")
+    }
+
+    fn play(&self, sound_path_str: &str, options: PlaybackOptions) -> Result<(), ThemerError> {
+        play_sound_file_to_sink(sound_path_str, options, &mut DeviceSink::new())
+    }
+}
+
+/// # Documentation
+/// Play a sound with an external player, where `get_command` gives the program and arguments which play a sound file.
+/// If the sound must be trimmed or faded, a rendered copy is played to its end instead of the player being stopped early.
+/// If the player can't change the volume (`sets_volume` is false), the volume is also applied to the rendered copy
+///
+/// # Errors
+/// Returns an error if the sound could be decoded, but the rendered copy could not be written
+/// Returns an error if the volume must be applied to the sound, but it can't be decoded
+/// Returns an error if `get_command` fails
+/// Returns an error if `run_player_command()` fails
+fn run_external_player<F>(
+    sound_path_str: &str,
+    options: PlaybackOptions,
+    sets_volume: bool,
+    get_command: F,
+) -> Result<(), ThemerError>
+where
+    F: Fn(&str, PlaybackOptions) -> Result<(String, Vec<String>), ThemerError>,
+{
+    let applies_volume = !sets_volume && (options.volume - 1.0).abs() > f32::EPSILON;
+
+    if options.needs_render() || applies_volume {
+        // The volume is left to the player, unless it can't set the volume itself
+        let (render_volume, player_volume) = if applies_volume {
+            (options.volume, 1.0)
         } else {
-            None
+            (1.0, options.volume)
+        };
+
+        match render_sound_to_temp_file(
+            sound_path_str,
+            PlaybackOptions {
+                volume: render_volume,
+                ..options
+            },
+        ) {
+            Ok(rendered_file) => {
+                // The rendered sound already begins at the start offset
+                let (program, args) = get_command(
+                    &rendered_file.path_str(),
+                    PlaybackOptions {
+                        start: None,
+                        volume: player_volume,
+                        ..options
+                    },
+                )?;
+
+                return run_player_command(program, &args, None);
+            }
+            // Sounds which can't be decoded are stopped by terminating the player instead, but can't start at an offset
+            Err(ThemerError::SoundDecoderError(_)) if options.start.is_none() && !applies_volume => {}
+            Err(e) => return Err(e),
         }
-    };
+    }
+
+    let (program, args) = get_command(sound_path_str, options)?;
+
+    run_player_command(program, &args, options.duration)
+}
+
+/// # Errors
+/// Returns an error if `spawn()` fails to execute the player
+fn run_player_command<S: AsRef<str>>(program: S, args: &[String], stop_after: Option<Duration>) -> Result<(), ThemerError> {
+    let args = args.iter().map(String::as_str).collect::<Vec<_>>();
+
+    let stop_count = get_stop_count();
 
     // Spawn the process
-    let mut child = spawn("pw-play", &[sound_path_str.as_str()])?;
+    let mut child = spawn(program.as_ref(), &args)?;
+    let started = Instant::now();
+
+    // Exit the command early if the duration has passed, or `stop_sounds()` has been called
+    while matches!(child.try_wait(), Ok(None)) {
+        let remaining = stop_after.map(|duration| duration.saturating_sub(started.elapsed()));
+
+        if get_stop_count() != stop_count || remaining == Some(Duration::ZERO) {
+            stop_child(&mut child);
+            break;
+        }
 
-    // If a duration was set, exit command early
-    if let Some(duration) = duration {
-        thread::sleep(duration);
-        let _ = child.kill(); // Send SIGKILL
+        thread::sleep(remaining.map_or(STOP_POLL_INTERVAL, |remaining| remaining.min(STOP_POLL_INTERVAL)));
     }
 
     let _ = child.wait(); // Wait until process ended
@@ -74,72 +663,478 @@ pub fn play_sound<S: AsRef<str> + Clone>(sound_name: S, duration: Option<Playbac
     Ok(())
 }
 
+/// # Documentation
+/// Ask a child process to stop with SIGTERM, only sending SIGKILL if it hasn't stopped after `STOP_GRACE_PERIOD`
+fn stop_child(child: &mut Child) {
+    // The process has already exited
+    if !matches!(child.try_wait(), Ok(None)) {
+        return;
+    }
+
+    let terminated = libc::pid_t::try_from(child.id())
+        // SAFETY: `kill()` only sends a signal, and the child hasn't been waited on, so its pid can't have been reused
+        .is_ok_and(|pid| unsafe { libc::kill(pid, libc::SIGTERM) } == 0);
+
+    if terminated {
+        let start = Instant::now();
+        while start.elapsed() < STOP_GRACE_PERIOD {
+            if !matches!(child.try_wait(), Ok(None)) {
+                return;
+            }
+
+            thread::sleep(STOP_POLL_INTERVAL);
+        }
+    }
+
+    let _ = child.kill(); // Send SIGKILL
+}
+
+/// # Documentation
+/// A rendered sound file, which is removed when it is dropped
+#[derive(Debug)]
+pub struct TempSoundFile {
+    path: PathBuf,
+}
+
+impl TempSoundFile {
+    #[must_use]
+    pub fn path_str(&self) -> String {
+        self.path.display().to_string()
+    }
+}
+
+impl Drop for TempSoundFile {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+/// # Documentation
+/// Render the sound using `render_sound()`, then write it to a WAV file in the private runtime directory
+///
+/// # Errors
+/// Returns an error if `render_sound()` fails
+/// Returns an error if `get_private_runtime_dir()` fails
+/// Returns an error if the WAV file already exists, or could not be written
+pub fn render_sound_to_temp_file<S: AsRef<str>>(
+    sound_path_str: S,
+    options: PlaybackOptions,
+) -> Result<TempSoundFile, ThemerError> {
+    let sound = render_sound(sound_path_str, options)?;
+
+    // Each rendered file has a unique name, so multiple sounds can be rendered at once
+    let path = PathBuf::from(format!(
+        "{}/sound_themer-{}-{}.wav",
+        get_private_runtime_dir()?,
+        std::process::id(),
+        TEMP_FILE_COUNTER.fetch_add(1, Ordering::Relaxed)
+    ));
+
+    // The file is never written if it already exists, so a file which was left behind is never overwritten
+    let mut sink = WavSink::new_private(&path);
+    sink.write(&sound.samples, sound.spec)?;
+    sink.finish()?;
+
+    // Only a file which was created here is removed once it has been played
+    Ok(TempSoundFile { path })
+}
+
+/// # Documentation
+/// Split a command template into its arguments on whitespace, keeping anything inside single or double quotes together
+///
+/// # Errors
+/// Returns an error if a quote is not closed
+pub fn split_command_template<S: AsRef<str>>(template: S) -> Result<Vec<String>, ThemerError> {
+    let mut args = Vec::new();
+    let mut current_arg: Option<String> = None;
+    let mut quote = None;
+
+    for c in template.as_ref().chars() {
+        match (quote, c) {
+            // Close the current quote
+            (Some(q), c) if c == q => quote = None,
+            // Open a new quote
+            (None, '\'' | '"') => {
+                quote = Some(c);
+                current_arg.get_or_insert_with(String::new);
+            }
+            // Whitespace outside of quotes ends the current argument
+            (None, c) if c.is_whitespace() => args.extend(current_arg.take()),
+            (_, c) => current_arg.get_or_insert_with(String::new).push(c),
+        }
+    }
+
+    if quote.is_some() {
+        return Err(ThemerError::PlayerConfigError(format!(
+            "Unclosed quote in player command '{}'",
+            template.as_ref()
+        )));
+    }
+
+    args.extend(current_arg);
+
+    Ok(args)
+}
+
+/// # Documentation
+/// Find the path of an executable called `program` in `$PATH`
+#[must_use]
+pub fn find_in_path<S: AsRef<str>>(program: S) -> Option<PathBuf> {
+    std::env::var_os("PATH").and_then(|paths| {
+        std::env::split_paths(&paths)
+            .map(|dir| dir.join(program.as_ref()))
+            .find(|path| {
+                path.metadata()
+                    .is_ok_and(|metadata| metadata.is_file() && metadata.permissions().mode() & 0o111 != 0)
+            })
+    })
+}
+
+/// # Documentation
+/// Get the `PlayerBackend` set by the `[player]` section of the config
+///
+/// # Errors
+/// Returns an error if the `native` backend is selected, but the `native` feature wasn't enabled
+/// Returns an error if the `command` backend is selected, but no command is set
+/// Returns an error if `auto` is selected, but no player could be found
+pub fn get_player_backend(player_config: &PlayerConfig) -> Result<Box<dyn PlayerBackend>, ThemerError> {
+    match (player_config.backend, &player_config.command) {
+        // A custom command is used whenever one is set, unless another backend is explicitly chosen
+        (PlayerBackendKind::Auto | PlayerBackendKind::Command, Some(command)) => Ok(Box::new(CommandPlayer::new(command))),
+        (PlayerBackendKind::Command, None) => Err(ThemerError::PlayerConfigError(String::from(
+            "The 'command' backend requires 'command' to be set in [player]",
+        ))),
+        (PlayerBackendKind::Auto, None) => detect_player_backend(),
+        (PlayerBackendKind::PwPlay, _) => Ok(Box::new(BuiltinPlayer::PwPlay)),
+        (PlayerBackendKind::Paplay, _) => Ok(Box::new(BuiltinPlayer::Paplay)),
+        (PlayerBackendKind::Aplay, _) => Ok(Box::new(BuiltinPlayer::Aplay)),
+        (PlayerBackendKind::Ffplay, _) => Ok(Box::new(BuiltinPlayer::Ffplay)),
+        #[cfg(feature = "native")]
+        (PlayerBackendKind::Native, _) => Ok(Box::new(NativePlayer)),
+        #[cfg(not(feature = "native"))]
+        (PlayerBackendKind::Native, _) => Err(ThemerError::PlayerConfigError(String::from(
+            "The 'native' backend requires sound_themer to be built with the 'native' feature",
+        ))),
+    }
+}
+
+/// # Documentation
+/// Use the native backend if it was compiled in, otherwise use the first built-in player which is found in `$PATH`
+///
 /// # Errors
-/// Returns an error if `get_theme_path()` fails
-/// Returns an error if `get_config()` fails
-/// Returns an error if `sound_path` doesn't exist
+/// Returns an error if no player could be found
+#[allow(clippy::unnecessary_wraps)]
+fn detect_player_backend() -> Result<Box<dyn PlayerBackend>, ThemerError> {
+    #[cfg(feature = "native")]
+    return Ok(Box::new(NativePlayer));
+
+    #[cfg(not(feature = "native"))]
+    find_builtin_player(|player| find_in_path(player.to_string()).is_some())
+        .map(|player| Box::new(player) as Box<dyn PlayerBackend>)
+        .ok_or_else(|| {
+            ThemerError::PlayerNotFoundError(
+                BuiltinPlayer::iter()
+                    .map(|player| format!("'{player}'"))
+                    .collect::<Vec<_>>()
+                    .join(" "),
+            )
+        })
+}
+
+/// # Documentation
+/// Get the first built-in player, in the order they are detected, which `is_installed` gives true for
+#[cfg(not(feature = "native"))]
+fn find_builtin_player<F: Fn(BuiltinPlayer) -> bool>(is_installed: F) -> Option<BuiltinPlayer> {
+    BuiltinPlayer::iter().find(|player| is_installed(*player))
+}
+
+/// # Errors
+/// Returns an error if `get_selected_theme()` fails
+/// Returns an error if the mapped names could not be picked using `pick_mapped_names()`
+/// Returns an error if `find_sound_from_names()` fails
 pub fn get_sound_from_name<S: AsRef<str>>(sound_name: S) -> Result<String, ThemerError> {
-    let theme_paths = get_selected_theme_paths()?;
     let theme = get_selected_theme()?;
-    let sound_ext = theme.sound_ext;
 
-    // Convert sound_name to MappingKey and map to its associated value (otherwise use sound_name as it is)
-    let mapping_entry = MappingKey::from_str(sound_name.as_ref())
-        .ok()
-        .and_then(|key| theme.mapping.get(&key).cloned()) // Map to associated value in Mapping
-        .unwrap_or_else(|| MappingEntry::Simple(sound_name.as_ref().to_string())); // If no associated value is found, use sound_name as it is
+    // Map sound_name to its associated value in the mapping (otherwise use sound_name as it is)
+    let mapping_entry = get_mapping_entry(&theme, sound_name.as_ref())
+        .filter(|mapping_entry| !mapping_entry.is_sequence())
+        .unwrap_or_else(|| MappingEntry::Simple(sound_name.as_ref().to_string())); // If no associated value (Or only a sequence) is found, use sound_name as it is
+    let mapped_names = pick_mapped_names(&theme, sound_name.as_ref(), &mapping_entry)?;
 
-    let mut checked_paths = Vec::new();
+    // Try the full names first, then fall back to less specific names (e.g: dialog-warning-auth --> dialog-warning --> dialog)
+    let mut search_names = mapped_names.clone();
+    for fallback_name in mapped_names.iter().flat_map(get_fallback_names) {
+        if !search_names.contains(&fallback_name) {
+            search_names.push(fallback_name);
+        }
+    }
 
-    for theme_path_str in theme_paths {
-        let sound_path_str = format!("{theme_path_str}/{mapping_entry}.{sound_ext}");
+    find_sound_from_names(&search_names)
+}
 
-        // Check if the sound file exists, return it if it does
-        let sound_path = Path::new(&sound_path_str);
-        if sound_path.exists() {
-            return Ok(sound_path_str);
-        }
+/// # Documentation
+/// Find the sound file for the first of `search_names` which has one in the selected theme, without using the mapping.
+/// The file found for each name is cached until one of the theme's directories changes
+///
+/// # Errors
+/// Returns an error if `get_sound_search_directories()` fails
+/// Returns an error if `lock_sound_cache()` fails
+/// Returns an error if none of `search_names` have a sound file
+pub fn find_sound_from_names(search_names: &[String]) -> Result<String, ThemerError> {
+    let theme = get_selected_theme()?;
+    let locale = get_locale()?;
+    let directories = get_sound_search_directories()?;
 
-        // If it doesn't exist then add it to the checked files paths
-        checked_paths.push(sound_path_str);
+    let mut cache = lock_sound_cache()?;
+    let mut changed = !cache.has_theme(&theme.name, locale.as_deref(), &directories);
+    let cached_theme = cache.get_theme_mut(&theme.name, locale.as_deref(), &directories);
+
+    let sound_path = search_names.iter().find_map(|name| {
+        cached_theme
+            .sounds
+            .entry(name.clone())
+            .or_insert_with(|| {
+                changed = true;
+
+                CachedSound {
+                    path: find_sound_file(&directories, name),
+                    info: None,
+                }
+            })
+            .path
+            .clone()
+    });
+
+    // The sound is still played if the cache can't be written
+    if changed {
+        let _ = cache.save();
     }
+    drop(cache);
 
     // No sound file was found in any of the theme path folders
-    Err(ThemerError::SoundPathsNotFoundError(
-        // Convert the checked paths to the correct format for the ThemerError
-        checked_paths
-            .iter()
-            .map(|file| format!("'{file}'"))
-            .collect::<Vec<_>>()
-            .join(" "),
-    ))
+    sound_path.ok_or_else(|| {
+        ThemerError::SoundPathsNotFoundError(
+            // Convert the checked paths to the correct format for the ThemerError
+            search_names
+                .iter()
+                .flat_map(|name| get_sound_file_paths(&directories, name))
+                .map(|file| format!("'{file}'"))
+                .collect::<Vec<_>>()
+                .join(" "),
+        )
+    })
+}
+
+/// # Documentation
+/// Get every directory which is searched for the selected theme's sounds, in the order they are searched
+///
+/// # Errors
+/// Returns an error if `get_selected_theme_lookup_paths()` fails
+/// Returns an error if `get_locale()` fails
+pub fn get_sound_search_directories() -> Result<Vec<CachedDirectory>, ThemerError> {
+    let lookup_paths = get_selected_theme_lookup_paths()?;
+
+    // Localised sounds are in subdirectories named after the locale, with the unlocalised directory checked last
+    let locale_dirs = get_locale()?
+        .map(get_locale_variants)
+        .unwrap_or_default()
+        .into_iter()
+        .map(|locale| format!("/{locale}"))
+        .chain(iter::once(String::new()))
+        .collect::<Vec<_>>();
+
+    // Search the selected theme, then its inherited themes, then the default theme
+    Ok(lookup_paths
+        .iter()
+        .flat_map(|(theme_path_str, sound_exts)| {
+            locale_dirs
+                .iter()
+                .map(move |locale_dir| CachedDirectory::new(format!("{theme_path_str}{locale_dir}"), sound_exts.clone()))
+        })
+        .collect())
+}
+
+// Get the path which a sound file for `name` would have in each directory, with the theme's extensions in order of priority
+fn get_sound_file_paths(directories: &[CachedDirectory], name: &str) -> Vec<String> {
+    directories
+        .iter()
+        .flat_map(|directory| {
+            directory
+                .sound_exts
+                .iter()
+                .map(move |sound_ext| format!("{}/{name}.{sound_ext}", directory.path))
+        })
+        .collect()
+}
+
+// Find the first sound file for `name` which exists
+fn find_sound_file(directories: &[CachedDirectory], name: &str) -> Option<String> {
+    get_sound_file_paths(directories, name)
+        .into_iter()
+        .find(|sound_path_str| Path::new(sound_path_str).exists())
+}
+
+/// # Documentation
+/// Find and probe every sound file in the selected theme's directories, then add them to the sound cache, returning how
+/// many sounds were cached
+///
+/// # Errors
+/// Returns an error if `get_sound_search_directories()` fails
+/// Returns an error if `lock_sound_cache()` or `SoundCacheGuard::save()` fails
+pub fn cache_selected_theme_sounds() -> Result<usize, ThemerError> {
+    let theme = get_selected_theme()?;
+    let locale = get_locale()?;
+    let directories = get_sound_search_directories()?;
+
+    // Every name which has a sound file in any of the directories
+    let sound_names = directories
+        .iter()
+        .filter_map(|directory| fs::read_dir(&directory.path).ok().map(|entries| (directory, entries)))
+        .flat_map(|(directory, entries)| {
+            entries.flatten().filter_map(|entry| {
+                let path = entry.path();
+
+                if path.is_file()
+                    && let Some(ext) = path.extension()
+                    && directory.sound_exts.iter().any(|sound_ext| ext == OsStr::new(sound_ext))
+                {
+                    path.file_stem().map(|file_name| file_name.display().to_string())
+                } else {
+                    None
+                }
+            })
+        })
+        .collect::<BTreeSet<_>>();
+
+    // Every file is probed before the cache is locked, so other threads can still use it
+    let sounds = sound_names
+        .iter()
+        .map(|name| {
+            let path = find_sound_file(&directories, name);
+            // Files which can't be probed are still cached, so they are found without searching
+            let info = path.as_ref().and_then(|path| probe_sound_info(path).ok());
+
+            (name.clone(), CachedSound { path, info })
+        })
+        .collect::<Vec<_>>();
+
+    let mut cache = lock_sound_cache()?;
+    cache
+        .get_theme_mut(&theme.name, locale.as_deref(), &directories)
+        .sounds
+        .extend(sounds);
+    cache.save()?;
+    drop(cache);
+
+    Ok(sound_names.len())
+}
+
+/// # Documentation
+/// Pick the names to search for from the mapping entry of `key`, using the entry's `PickMode`.
+/// Every name is given in order for `PickMode::FirstExisting`, otherwise only the picked name is given
+///
+/// # Errors
+/// Returns an error if a weighted name could not be picked
+pub fn pick_mapped_names<S: AsRef<str>>(theme: &Theme, key: S, mapping_entry: &MappingEntry) -> Result<Vec<String>, ThemerError> {
+    let candidates = mapping_entry.candidates();
+
+    // There is nothing to pick between
+    if candidates.len() <= 1 {
+        return Ok(candidates.into_iter().map(|candidate| candidate.name).collect());
+    }
+
+    let mut rng = rand::rng();
+
+    let picked = match mapping_entry.pick() {
+        PickMode::FirstExisting => return Ok(candidates.into_iter().map(|candidate| candidate.name).collect()),
+        PickMode::Random => candidates.choose(&mut rng),
+        PickMode::Weighted => Some(
+            candidates
+                .choose_weighted(&mut rng, |candidate| candidate.weight)
+                .map_err(|e| ThemerError::MappingPickError {
+                    key: key.as_ref().to_string(),
+                    e: e.to_string(),
+                })?,
+        ),
+        // Round-robin entries are tracked separately for each theme
+        PickMode::RoundRobin => candidates.get(next_round_robin_index(
+            format!("{}/{}", theme.name, key.as_ref()),
+            candidates.len(),
+        )),
+    };
+
+    Ok(picked.map(|candidate| vec![candidate.name.clone()]).unwrap_or_default())
+}
+
+/// # Documentation
+/// Get `sound_name` followed by each less specific name, by stripping dash-separated suffixes as described in the
+/// freedesktop Sound Naming Specification (e.g: `dialog-warning-auth` --> `dialog-warning` --> `dialog`)
+#[must_use]
+pub fn get_fallback_names<S: AsRef<str>>(sound_name: S) -> Vec<String> {
+    let mut fallback_names = vec![sound_name.as_ref().to_string()];
+
+    let mut name = sound_name.as_ref();
+    while let Some((prefix, _)) = name.rsplit_once('-') {
+        // Ignore empty prefixes caused by leading or repeated dashes
+        if !prefix.is_empty() && !prefix.ends_with('-') {
+            fallback_names.push(prefix.to_string());
+        }
+
+        name = prefix;
+    }
+
+    fallback_names
 }
 
 /// # Errors
 /// Returns an error if the sound file path could not be gotten via `get_sound_from_name()`
-/// Returns an error if the sound file path could not be opened as a `File`
-/// Returns an error if the file probe could not be created with the given arguments
-/// Returns an error if the default track couldn't be acquired
-/// Returns an error if the sample rate could not be acquired
-/// Returns an error if the sample count could not be acquired
+/// Returns an error if `get_sound_duration_from_path()` fails
 pub fn get_sound_duration_from_name<S: AsRef<str>>(sound_name: S) -> Result<Duration, ThemerError> {
-    let sound_file_str = get_sound_from_name(sound_name)?;
+    get_sound_duration_from_path(get_sound_from_name(sound_name)?)
+}
 
-    let sound_file = File::open(sound_file_str.clone()).map_err(|e| ThemerError::FileReadWriteError(e.to_string()))?;
+/// # Errors
+/// Returns an error if `get_sound_info_from_path()` fails
+pub fn get_sound_duration_from_path<S: AsRef<str>>(sound_path_str: S) -> Result<Duration, ThemerError> {
+    Ok(get_sound_info_from_path(sound_path_str)?.duration())
+}
 
-    // Get the MediaSourceStream for this file
-    let mss = MediaSourceStream::new(Box::new(sound_file), MediaSourceStreamOptions::default());
+/// # Documentation
+/// Get the details of a sound file from the sound cache, or by probing it if it has changed since it was cached
+///
+/// # Errors
+/// Returns an error if `lock_sound_cache()` fails
+/// Returns an error if `probe_sound_info()` fails
+pub fn get_sound_info_from_path<S: AsRef<str>>(sound_path_str: S) -> Result<SoundInfo, ThemerError> {
+    let modified = get_modified_time(sound_path_str.as_ref());
 
-    // Probe the file to get its format
-    let probe = get_probe()
-        .format(
-            &Hint::default(),
-            mss,
-            &symphonia::core::formats::FormatOptions::default(),
-            &MetadataOptions::default(),
-        )
-        .map_err(|e| ThemerError::SoundDecoderError(e.to_string()))?;
-    let format = probe.format;
+    let cached_info = lock_sound_cache()?.get_sound_info(sound_path_str.as_ref(), modified);
+    if let Some(info) = cached_info {
+        return Ok(info);
+    }
+
+    // The cache isn't locked while the file is probed, so other threads can still use it
+    let info = probe_sound_info(sound_path_str.as_ref())?;
+
+    // Only files which were found through a theme are cached, and the sound is still played if the cache can't be written
+    let mut cache = lock_sound_cache()?;
+    if cache.set_sound_info(sound_path_str.as_ref(), info) {
+        let _ = cache.save();
+    }
+    drop(cache);
+
+    Ok(info)
+}
+
+/// # Errors
+/// Returns an error if `probe_sound_file()` fails
+/// Returns an error if the default track couldn't be acquired
+/// Returns an error if the sample rate could not be acquired
+/// Returns an error if the sample count could not be acquired
+pub fn probe_sound_info<S: AsRef<str>>(sound_path_str: S) -> Result<SoundInfo, ThemerError> {
+    let sound_file_str = sound_path_str.as_ref();
+
+    let format = probe_sound_file(sound_file_str)?;
 
     // Get the default track from the format, then get the codec parameters
     let track = format
@@ -148,11 +1143,9 @@ pub fn get_sound_duration_from_name<S: AsRef<str>>(sound_name: S) -> Result<Dura
     let params = &track.codec_params;
 
     // Get the sample rate and sample count for this sound file
-    let sample_rate = f64::from(
-        params
-            .sample_rate
-            .ok_or_else(|| ThemerError::SoundDecoderError(format!("Sample rate not found for '{sound_file_str}'")))?,
-    );
+    let sample_rate = params
+        .sample_rate
+        .ok_or_else(|| ThemerError::SoundDecoderError(format!("Sample rate not found for '{sound_file_str}'")))?;
     let sample_count = f64::from(
         params
             .n_frames
@@ -160,5 +1153,290 @@ pub fn get_sound_duration_from_name<S: AsRef<str>>(sound_name: S) -> Result<Dura
             as u32,
     );
 
-    Ok(Duration::from_secs_f64(sample_count / sample_rate))
+    Ok(SoundInfo {
+        modified: get_modified_time(sound_file_str),
+        duration: sample_count / f64::from(sample_rate),
+        sample_rate,
+        channels: params.channels.and_then(|channels| u16::try_from(channels.count()).ok()),
+    })
+}
+
+/// # Documentation
+/// Decode the sound file with symphonia, then cut it at the exact samples at `options.start` and after `options.duration`,
+/// fade out its end, and scale it by `options.volume`
+///
+/// # Errors
+/// Returns an error if `decode_sound_file()` fails
+pub fn render_sound<S: AsRef<str>>(sound_path_str: S, options: PlaybackOptions) -> Result<DecodedSound, ThemerError> {
+    let mut sound = decode_sound_file(sound_path_str)?;
+
+    if let Some(start) = options.start {
+        sound.skip(start);
+    }
+
+    if let Some(duration) = options.duration {
+        sound.truncate(duration);
+    }
+
+    if let Some(fade_out) = options.fade_out {
+        sound.fade_out(fade_out);
+    }
+
+    sound.scale(options.volume);
+
+    Ok(sound)
+}
+
+/// # Documentation
+/// Render the sound file using `render_sound()` and write it to `sink`
+///
+/// # Errors
+/// Returns an error if `render_sound()` fails
+/// Returns an error if the samples could not be written to `sink`
+pub fn play_sound_file_to_sink<S: AsRef<str>>(
+    sound_path_str: S,
+    options: PlaybackOptions,
+    sink: &mut dyn AudioSink,
+) -> Result<(), ThemerError> {
+    let sound = render_sound(sound_path_str, options)?;
+
+    sink.write(&sound.samples, sound.spec)?;
+    sink.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn overridden_duration_ignores_mapped_end() -> Result<(), ThemerError> {
+        let overrides = PlaybackOverrides {
+            duration: Some(PlaybackDuration::from_str("200ms")?),
+            ..PlaybackOverrides::default()
+        };
+        let mapped_range = PlaybackRange::from_str("100ms..1s")?;
+
+        assert_eq!(overrides.merge_range(mapped_range).to_string(), "0.1s..");
+
+        Ok(())
+    }
+
+    #[test]
+    fn overridden_range_end_is_kept() -> Result<(), ThemerError> {
+        let overrides = PlaybackOverrides {
+            duration: Some(PlaybackDuration::from_str("200ms")?),
+            range: PlaybackRange::from_str("..500ms")?,
+            ..PlaybackOverrides::default()
+        };
+        let mapped_range = PlaybackRange::from_str("100ms..1s")?;
+
+        assert_eq!(overrides.merge_range(mapped_range).to_string(), "0.1s..0.5s");
+
+        Ok(())
+    }
+
+    #[test]
+    fn mapped_end_is_kept_without_overridden_duration() -> Result<(), ThemerError> {
+        let mapped_range = PlaybackRange::from_str("..1s")?;
+
+        assert_eq!(PlaybackOverrides::default().merge_range(mapped_range).to_string(), "..1s");
+
+        Ok(())
+    }
+
+    fn to_strings(strs: &[&str]) -> Vec<String> {
+        strs.iter().map(ToString::to_string).collect()
+    }
+
+    #[test]
+    fn command_template_is_split_on_unquoted_whitespace() -> Result<(), ThemerError> {
+        assert_eq!(
+            split_command_template("mpv  --no-video\t--volume={volume_percent} {path}")?,
+            to_strings(&["mpv", "--no-video", "--volume={volume_percent}", "{path}"])
+        );
+        assert_eq!(
+            split_command_template("'/opt/my player/play' \"{path}\" --title='a \"b\"'")?,
+            to_strings(&["/opt/my player/play", "{path}", "--title=a \"b\""])
+        );
+        // Adjacent quoted parts are joined, and empty quotes give an empty argument
+        assert_eq!(split_command_template("a'b c'\"d\" '' x")?, to_strings(&["ab cd", "", "x"]));
+        assert_eq!(split_command_template("  ")?, Vec::<String>::new());
+        assert!(split_command_template("play 'unclosed {path}").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn command_placeholders_are_replaced() -> Result<(), ThemerError> {
+        let template_args = split_command_template("'/opt/my player/play' {path} -v{volume} -p={volume_percent}% -t={duration}")?;
+        let options = PlaybackOptions {
+            volume: 0.5,
+            duration: Some(Duration::from_millis(250)),
+            ..PlaybackOptions::default()
+        };
+
+        // Paths with spaces stay as one argument, since the template is split before the path is substituted
+        let (program, args) = fill_command_template(&template_args, "/tmp/my sounds/bell.oga", options)?;
+        assert_eq!(program, "/opt/my player/play");
+        assert_eq!(args, to_strings(&["/tmp/my sounds/bell.oga", "-v0.5", "-p=50%", "-t=0.25"]));
+
+        // The duration is empty when it isn't set
+        let (_, args) = fill_command_template(&template_args, "bell.oga", PlaybackOptions::default())?;
+        assert_eq!(args, to_strings(&["bell.oga", "-v1", "-p=100%", "-t="]));
+
+        assert!(fill_command_template(&[], "bell.oga", options).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn command_sets_volume_with_either_placeholder() {
+        assert!(CommandPlayer::new("mpv --volume={volume_percent} {path}").sets_volume());
+        assert!(CommandPlayer::new("play -v {volume} {path}").sets_volume());
+        assert!(!CommandPlayer::new("play {path}").sets_volume());
+    }
+
+    #[test]
+    fn builtin_player_args() {
+        let options = PlaybackOptions {
+            volume: 0.5,
+            duration: Some(Duration::from_millis(1500)),
+            ..PlaybackOptions::default()
+        };
+
+        assert_eq!(
+            BuiltinPlayer::PwPlay.get_args("/tmp/bell.oga", options),
+            to_strings(&["--volume", "0.5", "/tmp/bell.oga"])
+        );
+        assert_eq!(
+            BuiltinPlayer::Paplay.get_args("/tmp/bell.oga", options),
+            to_strings(&["--volume=32768", "/tmp/bell.oga"])
+        );
+        assert_eq!(
+            BuiltinPlayer::Aplay.get_args("/tmp/bell.oga", options),
+            to_strings(&["-q", "/tmp/bell.oga"])
+        );
+        assert_eq!(
+            BuiltinPlayer::Ffplay.get_args("/tmp/bell.oga", options),
+            to_strings(&[
+                "-nodisp",
+                "-autoexit",
+                "-loglevel",
+                "quiet",
+                "-volume",
+                "50",
+                "-t",
+                "1.5",
+                "/tmp/bell.oga"
+            ])
+        );
+        assert_eq!(
+            BuiltinPlayer::Ffplay.get_args("/tmp/bell.oga", PlaybackOptions::default()),
+            to_strings(&[
+                "-nodisp",
+                "-autoexit",
+                "-loglevel",
+                "quiet",
+                "-volume",
+                "100",
+                "/tmp/bell.oga"
+            ])
+        );
+
+        assert!(!BuiltinPlayer::Aplay.sets_volume());
+        assert!(
+            [BuiltinPlayer::PwPlay, BuiltinPlayer::Paplay, BuiltinPlayer::Ffplay]
+                .into_iter()
+                .all(BuiltinPlayer::sets_volume)
+        );
+    }
+
+    #[test]
+    fn player_backend_from_config() -> Result<(), ThemerError> {
+        let get_name = |backend, command: Option<&str>| {
+            get_player_backend(&PlayerConfig {
+                backend,
+                command: command.map(ToString::to_string),
+            })
+            .map(|player| player.name())
+        };
+
+        // A command is used whenever one is set, unless another backend is chosen
+        assert_eq!(get_name(PlayerBackendKind::Auto, Some("play {path}"))?, "command");
+        assert_eq!(get_name(PlayerBackendKind::Command, Some("play {path}"))?, "command");
+        assert_eq!(get_name(PlayerBackendKind::Aplay, Some("play {path}"))?, "aplay");
+        assert_eq!(get_name(PlayerBackendKind::PwPlay, None)?, "pw-play");
+        assert!(matches!(
+            get_name(PlayerBackendKind::Command, None),
+            Err(ThemerError::PlayerConfigError(_))
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(not(feature = "native"))]
+    fn first_installed_builtin_player_is_detected() {
+        assert_eq!(find_builtin_player(|_| true), Some(BuiltinPlayer::PwPlay));
+        assert_eq!(
+            find_builtin_player(|player| matches!(player, BuiltinPlayer::Aplay | BuiltinPlayer::Ffplay)),
+            Some(BuiltinPlayer::Aplay)
+        );
+        assert_eq!(
+            find_builtin_player(|player| player == BuiltinPlayer::Ffplay),
+            Some(BuiltinPlayer::Ffplay)
+        );
+        assert_eq!(find_builtin_player(|_| false), None);
+    }
+
+    #[test]
+    fn sound_items_split_at_the_last_at() -> Result<(), ThemerError> {
+        let item = SoundItem::from_str("message@250ms")?;
+        assert_eq!(item.sound_name, "message");
+        assert!(matches!(item.duration, Some(PlaybackDuration::Time(duration)) if duration == Duration::from_millis(250)));
+
+        let item = SoundItem::from_str(" complete ")?;
+        assert_eq!(item.sound_name, "complete");
+        assert!(item.duration.is_none());
+
+        // Only the last '@' separates the duration, so names can contain an '@'
+        let item = SoundItem::from_str("user@host@50%")?;
+        assert_eq!(item.sound_name, "user@host");
+        assert!(matches!(item.duration, Some(PlaybackDuration::Percent(_))));
+
+        assert!(SoundItem::from_str("@1s").is_err());
+        assert!(SoundItem::from_str("").is_err());
+        assert!(SoundItem::from_str("bell@loud").is_err());
+        assert!(SoundItem::from_str("bell@").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn sound_items_round_trip_through_display() -> Result<(), ThemerError> {
+        for (item_str, expected) in [("bell", "bell"), ("bell@250ms", "bell@0.25s"), ("bell@50%", "bell@50%")] {
+            let item = SoundItem::from_str(item_str)?;
+            assert_eq!(item.to_string(), expected);
+            assert_eq!(SoundItem::from_str(&item.to_string())?.to_string(), expected);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn fallback_names_strip_dash_separated_suffixes() {
+        assert_eq!(
+            get_fallback_names("dialog-warning-auth"),
+            to_strings(&["dialog-warning-auth", "dialog-warning", "dialog"])
+        );
+        assert_eq!(get_fallback_names("bell"), to_strings(&["bell"]));
+
+        // Empty parts from leading, trailing, or repeated dashes aren't used as names
+        assert_eq!(get_fallback_names("-bell"), to_strings(&["-bell"]));
+        assert_eq!(get_fallback_names("bell-"), to_strings(&["bell-", "bell"]));
+        assert_eq!(
+            get_fallback_names("network--offline-x"),
+            to_strings(&["network--offline-x", "network--offline", "network"])
+        );
+    }
 }
@@ -0,0 +1,59 @@
+use std::{collections::HashMap, fs};
+
+use crate::{
+    atomic_file::{lock_file, write_file_atomically},
+    error::ThemerError,
+    xdg::get_state_home,
+};
+
+const STATE_DIR_SHORT: &str = "sound_themer";
+const ROUND_ROBIN_FILE_NAME: &str = "round_robin.toml";
+
+/// # Documentation
+/// Get the path of the file which stores the last index picked by each round-robin mapping entry
+///
+/// # Errors
+/// Returns an error if neither `$XDG_STATE_HOME` nor `$HOME` are set
+pub fn get_round_robin_path() -> Result<String, ThemerError> {
+    let state_home = get_state_home().ok_or_else(|| ThemerError::EnvironmentVarError(String::from("$XDG_STATE_HOME")))?;
+
+    Ok(format!("{state_home}/{STATE_DIR_SHORT}/{ROUND_ROBIN_FILE_NAME}"))
+}
+
+/// # Documentation
+/// Get the next index for the round-robin entry `key`, which has `count` names, then save it so the following call
+/// (Including from another invocation) moves on to the next name. The state only changes which name is picked, so if it
+/// can't be read or saved (e.g: `$HOME` is read-only) the first name is picked instead of the sound failing to play
+#[must_use]
+pub fn next_round_robin_index<S: AsRef<str>>(key: S, count: usize) -> usize {
+    update_round_robin_index(key, count).unwrap_or(0)
+}
+
+/// # Documentation
+/// Read, advance, then save the index for the round-robin entry `key`, while holding the lock on the state file so
+/// concurrent invocations each pick a different name
+///
+/// # Errors
+/// Returns an error if `get_round_robin_path()` fails
+/// Returns an error if `lock_file()` fails
+/// Returns an error if `write_file_atomically()` fails
+fn update_round_robin_index<S: AsRef<str>>(key: S, count: usize) -> Result<usize, ThemerError> {
+    let state_path_str = get_round_robin_path()?;
+    let _lock = lock_file(&state_path_str)?;
+
+    // A missing or unreadable state file starts every entry from its first name
+    let mut indices = fs::read_to_string(&state_path_str)
+        .ok()
+        .and_then(|state| toml::from_str::<HashMap<String, usize>>(&state).ok())
+        .unwrap_or_default();
+
+    let index = indices
+        .get(key.as_ref())
+        .map_or(0, |last_index| (last_index + 1) % count.max(1));
+    indices.insert(key.as_ref().to_string(), index);
+
+    let state = toml::to_string(&indices).map_err(|e| ThemerError::FileReadWriteError(e.to_string()))?;
+    write_file_atomically(&state_path_str, state)?;
+
+    Ok(index)
+}
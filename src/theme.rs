@@ -1,87 +1,367 @@
 use rand::prelude::*;
-use serde::Deserialize;
+use serde::{Deserialize, Deserializer, Serialize};
 
 use std::{
-    collections::HashMap,
+    collections::{HashMap, HashSet},
+    ffi::OsStr,
+    fs,
     path::Path,
-    sync::{LazyLock, Mutex},
+    str::FromStr,
+    sync::Mutex,
 };
 
-use crate::{config::get_toml_config, error::ThemerError, mapping::Mapping};
+use crate::{
+    config::{TOMLConfig, ValidationReport, ValidationSeverity, get_toml_config},
+    decode::probe_sound_file,
+    duration::{PlaybackDuration, PlaybackRange, playback_range_to_durations},
+    error::ThemerError,
+    index_theme::{DEFAULT_OUTPUT_PROFILE, INDEX_THEME_FILE_NAME, IndexTheme},
+    mapping::{Mapping, MappingCandidate, MappingEntry, SequenceStep},
+    sound::{PlaybackOverrides, get_fallback_names},
+    volume::Volume,
+    xdg::{get_data_dirs, get_data_home},
+};
 
 pub const DEFAULT_THEME_NAME: &str = "freedesktop";
-pub const DEFAULT_SOUND_EXT: &str = "oga";
+/// # Documentation
+/// The sound file extensions in the order preferred by the freedesktop Sound Theme Specification
+pub const DEFAULT_SOUND_EXTS: &[&str] = &["oga", "ogg", "wav"];
 pub const DEFAULT_SOUND_DIRS: &[&str] = &["stereo"];
+pub const SOUND_THEMES_DIR_SHORT: &str = "sounds";
 
-#[derive(Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Theme {
     pub name: String,
-    pub sound_ext: String,
-    #[serde(default = "get_default_directories")]
-    pub sound_dirs: Vec<String>,
+    /// # Documentation
+    /// The extensions of the sound files in order of priority, can also be set using a single string with `sound_ext`
+    #[serde(
+        default = "get_default_sound_exts",
+        alias = "sound_ext",
+        deserialize_with = "deserialize_sound_exts"
+    )]
+    pub sound_exts: Vec<String>,
+    /// # Documentation
+    /// The directories inside the theme folder, if unset they are read from the theme's `index.theme`
+    #[serde(default, alias = "directories", skip_serializing_if = "Option::is_none")]
+    pub sound_dirs: Option<Vec<String>>,
+    /// # Documentation
+    /// The theme whose mapping and sound directories this theme inherits, only overriding the values it sets itself
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub extends: Option<String>,
+    /// # Documentation
+    /// The themes to fall back to when a sound isn't found, if unset they are read from the theme's `index.theme`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub inherits: Option<Vec<String>>,
+    /// # Documentation
+    /// The `OutputProfile=` used to select directories from the theme's `index.theme`
+    #[serde(default = "get_default_output_profile")]
+    pub output_profile: String,
+    /// # Documentation
+    /// The volume of every sound in this theme, multiplied by the global, mapping, and `--volume` volumes
     #[serde(default)]
+    pub volume: Volume,
+    /// # Documentation
+    /// The theme's mapping, which is layered over the mapping of the theme it extends, or the global mapping
+    #[serde(default, skip_serializing_if = "Mapping::is_empty_layer")]
     pub mapping: Mapping,
 }
 
+fn get_default_sound_exts() -> Vec<String> {
+    DEFAULT_SOUND_EXTS.iter().map(ToString::to_string).collect()
+}
+
+/// # Documentation
+/// Deserialize either a single extension or a list of extensions, removing any leading '.' from them
+fn deserialize_sound_exts<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<String>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum SoundExts {
+        Single(String),
+        Multiple(Vec<String>),
+    }
+
+    let sound_exts = match SoundExts::deserialize(deserializer)? {
+        SoundExts::Single(sound_ext) => vec![sound_ext],
+        SoundExts::Multiple(sound_exts) => sound_exts,
+    };
+
+    Ok(sound_exts
+        .into_iter()
+        .map(|sound_ext| sound_ext.trim_start_matches('.').to_string())
+        .collect())
+}
+
 fn get_default_directories() -> Vec<String> {
     DEFAULT_SOUND_DIRS.iter().map(ToString::to_string).collect()
 }
 
+fn get_default_output_profile() -> String {
+    String::from(DEFAULT_OUTPUT_PROFILE)
+}
+
 impl Theme {
-    pub fn new<S: AsRef<str>, V: AsRef<[S]>>(name: S, sound_ext: S, sound_dirs: V, mapping: Mapping) -> Self {
+    pub fn new<S: AsRef<str>, V: AsRef<[S]>>(name: S, sound_exts: V, sound_dirs: V, mapping: Mapping) -> Self {
         Self {
             name: name.as_ref().to_string(),
-            sound_ext: sound_ext.as_ref().to_string(),
-            sound_dirs: sound_dirs
+            sound_exts: sound_exts
                 .as_ref()
                 .iter()
                 .map(AsRef::as_ref)
                 .map(ToString::to_string)
                 .collect(),
+            sound_dirs: Some(
+                sound_dirs
+                    .as_ref()
+                    .iter()
+                    .map(AsRef::as_ref)
+                    .map(ToString::to_string)
+                    .collect(),
+            ),
+            extends: None,
+            inherits: None,
+            output_profile: get_default_output_profile(),
+            volume: Volume::default(),
             mapping,
         }
     }
+
+    /// # Documentation
+    /// Create a `Theme` which only has an `index.theme` file, using the default values for everything not set by it
+    #[must_use]
+    pub fn from_index_theme<S: AsRef<str>>(name: S, index_theme: &IndexTheme) -> Self {
+        let mut theme = Self {
+            name: name.as_ref().to_string(),
+            sound_exts: get_default_sound_exts(),
+            sound_dirs: None,
+            extends: None,
+            inherits: None,
+            output_profile: get_default_output_profile(),
+            volume: Volume::default(),
+            mapping: Mapping::default(),
+        };
+        theme.apply_index_theme(index_theme);
+
+        theme
+    }
+
+    /// # Documentation
+    /// Set `sound_dirs` and `inherits` from `index_theme`, if they weren't already set in the config
+    pub fn apply_index_theme(&mut self, index_theme: &IndexTheme) {
+        if self.sound_dirs.is_none() {
+            self.sound_dirs = Some(index_theme.get_directories_for_profile(&self.output_profile));
+        }
+
+        if self.inherits.is_none() {
+            self.inherits = Some(index_theme.inherits.clone());
+        }
+    }
+
+    /// # Documentation
+    /// Get the directories inside the theme folder, using the default directories if none are set
+    #[must_use]
+    pub fn get_sound_dirs(&self) -> Vec<String> {
+        self.sound_dirs.clone().unwrap_or_else(get_default_directories)
+    }
+
+    /// # Documentation
+    /// Get the names of the themes which this theme inherits from
+    #[must_use]
+    pub fn get_inherits(&self) -> Vec<String> {
+        self.inherits.clone().unwrap_or_default()
+    }
 }
 
-static SELECTED_THEME: LazyLock<Mutex<Theme>> = LazyLock::new(|| Mutex::new(init_selected_theme()));
+// Only set once the selected theme has been loaded successfully, so a failed load is tried again
+static SELECTED_THEME: Mutex<Option<Theme>> = Mutex::new(None);
 
 // Resets `SELECTED_THEME` using `init_selected_theme()`
+/// # Errors
+/// Returns an error if `init_selected_theme()` fails
+/// Returns an error if `SELECTED_THEME` couldn't be locked
 #[doc(hidden)]
-pub fn reset_selected_theme() {
-    *SELECTED_THEME
-        .lock()
-        .unwrap_or_else(|e| panic!("{}", ThemerError::MutexLockError(e.to_string()))) = init_selected_theme();
+pub fn reset_selected_theme() -> Result<(), ThemerError> {
+    select_theme(init_selected_theme()?)
 }
 
 /// # Errors
-/// Returns an error if no `Theme` is mapped to `name`
-fn get_theme_from_name<S: AsRef<str>>(name: S) -> Result<Theme, ThemerError> {
-    let config = get_toml_config();
+/// Returns an error if `get_extended_theme_from_name()` fails
+/// Returns an error if the aliases in the theme's mapping could not be resolved
+pub fn get_theme_from_name<S: AsRef<str>>(name: S) -> Result<Theme, ThemerError> {
+    let mut theme = get_extended_theme_from_name(name, &mut Vec::new())?;
+
+    // Aliases are only resolved once every layer of the mapping is known, so they follow the keys which override them
+    theme.mapping.resolve_aliases()?;
+
+    Ok(theme)
+}
+
+/// # Documentation
+/// Resolve the aliases in the mapping of every theme in the config, so an alias which can't be resolved is reported
+/// when the config is read, instead of when the theme is first used
+///
+/// # Errors
+/// Returns an error if the aliases in any theme's mapping could not be resolved
+pub fn check_config_aliases(config: &TOMLConfig) -> Result<(), ThemerError> {
+    // Only the last definition of a theme is used, so the others aren't checked
+    let themes: HashMap<&str, &Theme> = config.themes.iter().map(|theme| (theme.name.as_str(), theme)).collect();
+    let base_mapping = config.mapping.layered_over(&Mapping::builtin());
+
+    base_mapping.clone().resolve_aliases()?;
+    for theme in themes.values() {
+        get_config_theme_mapping(theme, &themes, &base_mapping, &mut Vec::new()).resolve_aliases()?;
+    }
+
+    Ok(())
+}
+
+/// # Documentation
+/// Get the mapping of `theme` layered over the mappings of the themes it extends, using only the themes in the config.
+/// A cycle of themes stops at the global mapping, since it is reported when the theme is loaded
+fn get_config_theme_mapping(
+    theme: &Theme,
+    themes: &HashMap<&str, &Theme>,
+    base_mapping: &Mapping,
+    extending: &mut Vec<String>,
+) -> Mapping {
+    extending.push(theme.name.clone());
+
+    let extended_mapping = match theme.extends.as_deref().and_then(|extends| themes.get(extends)) {
+        Some(extended_theme) if !extending.contains(&extended_theme.name) => {
+            get_config_theme_mapping(extended_theme, themes, base_mapping, extending)
+        }
+        // A theme which is only defined by its `index.theme` has no mapping of its own
+        _ => base_mapping.clone(),
+    };
+
+    extending.pop();
+
+    theme.mapping.layered_over(&extended_mapping)
+}
+
+/// # Documentation
+/// Get the theme from the config or its `index.theme`, with its mapping layered over the mapping it inherits.
+/// `extending` holds the names of the themes which are extending this theme, so cycles can be found
+///
+/// # Errors
+/// Returns an error if no `Theme` is mapped to `name`, and no `index.theme` could be found for `name`
+/// Returns an error if the theme's `index.theme` could not be parsed
+/// Returns an error if the themes extend each other in a cycle
+/// Returns an error if `get_toml_config()` fails
+fn get_extended_theme_from_name<S: AsRef<str>>(name: S, extending: &mut Vec<String>) -> Result<Theme, ThemerError> {
+    let config = get_toml_config()?;
+
+    if extending.iter().any(|extending_name| extending_name == name.as_ref()) {
+        return Err(ThemerError::ThemeExtendsError {
+            name: name.as_ref().to_string(),
+            e: format!(
+                "Themes extend each other in a cycle: {} --> {}",
+                extending.join(" --> "),
+                name.as_ref()
+            ),
+        });
+    }
 
     let hashmap: HashMap<String, Theme> = config.themes.into_iter().map(|theme| (theme.name.clone(), theme)).collect();
 
-    hashmap
-        .get(name.as_ref())
-        .map_or_else(|| Err(ThemerError::HashMapEntryError(name.as_ref().to_string())), Ok)
-        .cloned()
+    let index_theme = get_index_theme(name.as_ref())?;
+
+    let mut theme = match (hashmap.get(name.as_ref()).cloned(), &index_theme) {
+        (Some(theme), _) => theme,
+        // The theme isn't in the config, so it is defined by its index.theme alone
+        (None, Some(index_theme)) => Theme::from_index_theme(name.as_ref(), index_theme),
+        (None, None) => return Err(ThemerError::HashMapEntryError(name.as_ref().to_string())),
+    };
+
+    // Inherit the values not set in the config from the extended theme, otherwise use the global mapping
+    let base_mapping = if let Some(extends) = theme.extends.clone() {
+        extending.push(name.as_ref().to_string());
+        let extended_theme = get_extended_theme_from_name(&extends, extending)?;
+        extending.pop();
+
+        if theme.sound_dirs.is_none() {
+            theme.sound_dirs = extended_theme.sound_dirs;
+        }
+
+        extended_theme.mapping
+    } else {
+        config.mapping.layered_over(&Mapping::builtin())
+    };
+    theme.mapping = theme.mapping.layered_over(&base_mapping);
+
+    // Fill in the values which are still not set using index.theme
+    if let Some(index_theme) = &index_theme {
+        theme.apply_index_theme(index_theme);
+    }
+
+    Ok(theme)
+}
+
+/// # Errors
+/// Returns an error if `get_theme_search_paths()` fails
+/// Returns an error if the theme's `index.theme` exists but could not be parsed
+fn get_index_theme<S: AsRef<str>>(name: S) -> Result<Option<IndexTheme>, ThemerError> {
+    // Use the first index.theme found in the search path
+    get_theme_search_paths()?
+        .into_iter()
+        .map(|search_path| format!("{search_path}/{}/{INDEX_THEME_FILE_NAME}", name.as_ref()))
+        .find(|index_path_str| Path::new(index_path_str).exists())
+        .map(IndexTheme::from_file)
+        .transpose()
+}
+
+/// # Documentation
+/// Get the ordered list of directories which contain sound themes.
+/// This is the `search_paths` in the config, then `$XDG_DATA_HOME/sounds`, then `sounds` in each entry of `$XDG_DATA_DIRS`
+///
+/// # Errors
+/// Returns an error if `get_toml_config()` fails
+pub fn get_theme_search_paths() -> Result<Vec<String>, ThemerError> {
+    let config_search_paths = get_toml_config()?.search_paths;
+
+    let xdg_search_paths = get_data_home()
+        .into_iter()
+        .chain(get_data_dirs())
+        .map(|data_dir| format!("{}/{SOUND_THEMES_DIR_SHORT}", data_dir.trim_end_matches('/')));
+
+    // Remove any duplicates, keeping the first occurrence of each path
+    let mut seen = HashSet::new();
+    Ok(config_search_paths
+        .into_iter()
+        .chain(xdg_search_paths)
+        .filter(|search_path| seen.insert(search_path.clone()))
+        .collect())
 }
 
-/// # Panics
-/// Panics if `get_theme_from_name()` fails
+/// # Errors
+/// Returns an error if `get_toml_config()` fails
+/// Returns an error if `get_theme_from_name()` fails, such as when `theme_name` isn't a known theme
 #[doc(hidden)]
-#[must_use]
-pub fn init_selected_theme() -> Theme {
-    let theme_name = get_toml_config().theme_name;
+pub fn init_selected_theme() -> Result<Theme, ThemerError> {
+    let theme_name = get_toml_config()?.theme_name;
 
-    get_theme_from_name(theme_name).unwrap_or_else(|e| panic!("{e}"))
+    get_theme_from_name(theme_name)
 }
 
 /// # Errors
 /// Returns an error if `SELECTED_THEME` couldn't be locked
+/// Returns an error if no theme has been selected yet, and `init_selected_theme()` fails
 pub fn get_selected_theme() -> Result<Theme, ThemerError> {
+    let selected_theme = SELECTED_THEME
+        .lock()
+        .map_err(|e| ThemerError::MutexLockError(e.to_string()))?
+        .clone();
+
+    if let Some(theme) = selected_theme {
+        return Ok(theme);
+    }
+
+    let theme = init_selected_theme()?;
+
+    // Keep the theme selected by another thread while this one was loading, if there is one
     Ok(SELECTED_THEME
         .lock()
         .map_err(|e| ThemerError::MutexLockError(e.to_string()))?
+        .get_or_insert(theme)
         .clone())
 }
 
@@ -106,52 +386,68 @@ pub fn select_theme(theme: Theme) -> Result<(), ThemerError> {
         let mut guard = SELECTED_THEME
             .lock()
             .map_err(|e| ThemerError::MutexLockError(e.to_string()))?;
-        (*guard) = theme;
+        (*guard) = Some(theme);
     }
 
     Ok(())
 }
 
 /// # Errors
+/// Returns an error if `get_toml_config()` fails
 /// Returns an error if `themes` in `TOMLConfig` is empty
 /// Returns an error if `select_theme()` fails
 pub fn select_random_theme() -> Result<(), ThemerError> {
-    let config = get_toml_config();
+    let config = get_toml_config()?;
     let mut rng = rand::rng();
 
     // Get a random theme from the config, mapping to EmptyThemesError if it fails
-    let theme =
-        config.themes.choose(&mut rng).cloned().ok_or_else(|| {
-            ThemerError::EmptyThemesError(String::from("Random theme could not be selected from empty Vec<Theme>"))
-        })?;
+    let theme = config
+        .themes
+        .choose(&mut rng)
+        .ok_or_else(|| ThemerError::EmptyThemesError(String::from("Random theme could not be selected from empty Vec<Theme>")))?;
 
-    // Set the new theme
-    select_theme(theme)?;
+    // Set the new theme (Including the values from its index.theme)
+    select_theme_by_name(&theme.name)?;
 
     Ok(())
 }
 
+/// # Documentation
+/// Split the directories of `theme` into the paths which exist, and the paths which don't.
+/// Each directory is searched for in every search path, so a user-level directory can override individual sounds
+///
 /// # Errors
-/// Returns an error if `get_selected_theme()` fails
-/// Returns an error if any of the theme paths don't exist
-pub fn get_selected_theme_paths() -> Result<Vec<String>, ThemerError> {
-    let theme = get_selected_theme()?;
+/// Returns an error if `get_theme_search_paths()` fails
+pub fn get_theme_paths(theme: &Theme) -> Result<(Vec<String>, Vec<String>), ThemerError> {
+    let search_paths = get_theme_search_paths()?;
 
-    let (theme_paths_str, path_errors) = theme
-        .sound_dirs
+    Ok(theme
+        .get_sound_dirs()
         .into_iter()
-        .map(|dir| format!("/usr/share/sounds/{}/{}", theme.name, dir))
-        .fold((Vec::new(), Vec::new()), |mut acc, path_str| {
-            // Check that the path exists
-            let path = Path::new(&path_str);
-            if path.exists() {
-                acc.0.push(path_str);
+        .fold((Vec::new(), Vec::new()), |mut acc, dir| {
+            let (found, missing): (Vec<_>, Vec<_>) = search_paths
+                .iter()
+                .map(|search_path| format!("{search_path}/{}/{dir}", theme.name))
+                .partition(|path_str| Path::new(path_str).exists());
+
+            // The directory is only missing if it couldn't be found in any search path
+            if found.is_empty() {
+                acc.1.extend(missing);
             } else {
-                acc.1.push(path_str);
+                acc.0.extend(found);
             }
 
             acc
-        });
+        }))
+}
+
+/// # Errors
+/// Returns an error if `get_selected_theme()` fails
+/// Returns an error if any of the theme paths don't exist
+pub fn get_selected_theme_paths() -> Result<Vec<String>, ThemerError> {
+    let theme = get_selected_theme()?;
+
+    let (theme_paths_str, path_errors) = get_theme_paths(&theme)?;
 
     // Check if there were any errors with reading the directories
     if path_errors.is_empty() {
@@ -167,3 +463,335 @@ pub fn get_selected_theme_paths() -> Result<Vec<String>, ThemerError> {
         ))
     }
 }
+
+/// # Documentation
+/// Get the selected theme followed by every theme it inherits from (depth-first), ending with the `freedesktop` theme
+///
+/// # Errors
+/// Returns an error if `get_selected_theme()` fails
+/// Returns an error if `get_theme_chain()` fails
+pub fn get_selected_theme_chain() -> Result<Vec<Theme>, ThemerError> {
+    get_theme_chain(get_selected_theme()?)
+}
+
+/// # Documentation
+/// Get `theme` followed by every theme it inherits from (depth-first), ending with the `freedesktop` theme
+///
+/// # Errors
+/// Returns an error if an inherited theme's `index.theme` could not be parsed
+pub fn get_theme_chain(theme: Theme) -> Result<Vec<Theme>, ThemerError> {
+    let mut chain = Vec::new();
+    let mut visited = HashSet::from([theme.name.clone()]);
+    push_theme_and_parents(theme, &mut chain, &mut visited)?;
+
+    // Every theme implicitly falls back to the default theme
+    if visited.insert(String::from(DEFAULT_THEME_NAME))
+        && let Ok(theme) = get_theme_from_name(DEFAULT_THEME_NAME)
+    {
+        chain.push(theme);
+    }
+
+    Ok(chain)
+}
+
+/// # Errors
+/// Returns an error if an inherited theme's `index.theme` could not be parsed
+fn push_theme_and_parents(theme: Theme, chain: &mut Vec<Theme>, visited: &mut HashSet<String>) -> Result<(), ThemerError> {
+    let inherits = theme.get_inherits();
+    chain.push(theme);
+
+    for parent_name in inherits {
+        // Skip themes that have already been added, this also prevents infinite loops
+        if !visited.insert(parent_name.clone()) {
+            continue;
+        }
+
+        // Inherited themes which aren't installed are ignored
+        match get_theme_from_name(&parent_name) {
+            Ok(parent) => push_theme_and_parents(parent, chain, visited)?,
+            Err(ThemerError::HashMapEntryError(_)) => {}
+            Err(e) => return Err(e),
+        }
+    }
+
+    Ok(())
+}
+
+/// # Documentation
+/// Get every directory which should be searched for a sound, paired with the sound extensions of its theme
+///
+/// # Errors
+/// Returns an error if `get_selected_theme_paths()` fails
+/// Returns an error if `get_theme_lookup_paths()` fails
+pub fn get_selected_theme_lookup_paths() -> Result<Vec<(String, Vec<String>)>, ThemerError> {
+    // The selected theme's directories must all exist
+    get_selected_theme_paths()?;
+
+    get_theme_lookup_paths(get_selected_theme()?)
+}
+
+/// # Documentation
+/// Get every directory which should be searched for a sound when `theme` is selected, paired with the sound extensions of
+/// the theme it belongs to. Directories which don't exist are skipped
+///
+/// # Errors
+/// Returns an error if `get_theme_chain()` or `get_theme_paths()` fails
+pub fn get_theme_lookup_paths(theme: Theme) -> Result<Vec<(String, Vec<String>)>, ThemerError> {
+    let mut lookup_paths = Vec::new();
+
+    for theme in get_theme_chain(theme)? {
+        let (paths, _) = get_theme_paths(&theme)?;
+        lookup_paths.extend(paths.into_iter().map(|path| (path, theme.sound_exts.clone())));
+    }
+
+    Ok(lookup_paths)
+}
+
+/// # Documentation
+/// Check `theme` for problems which would stop its sounds from playing as expected:
+/// - Directories which don't exist in any theme search path
+/// - Mapped names which have no sound file in the theme, or the themes it inherits from
+/// - Mapped durations, fade-outs, and ranges which are invalid, or longer than their sound file
+/// - Sound files which can't be decoded
+#[must_use]
+pub fn validate_theme(theme: &Theme) -> ValidationReport {
+    let mut report = ValidationReport::default();
+    let theme_name = Some(theme.name.as_str());
+
+    let search_paths = match get_theme_search_paths() {
+        Ok(search_paths) => search_paths,
+        Err(e) => {
+            report.push(ValidationSeverity::Error, theme_name, e.to_string());
+            return report;
+        }
+    };
+
+    // Each directory only has to exist in one of the search paths
+    let mut theme_paths = Vec::new();
+    for dir in theme.get_sound_dirs() {
+        let found = search_paths
+            .iter()
+            .map(|search_path| format!("{search_path}/{}/{dir}", theme.name))
+            .filter(|path_str| Path::new(path_str).is_dir())
+            .collect::<Vec<_>>();
+
+        if found.is_empty() {
+            report.push(
+                ValidationSeverity::Error,
+                theme_name,
+                format!("Directory '{dir}' could not be found in any of the theme search paths"),
+            );
+        }
+
+        theme_paths.extend(found);
+    }
+
+    let lookup_paths = match get_theme_lookup_paths(theme.clone()) {
+        Ok(lookup_paths) => lookup_paths,
+        Err(e) => {
+            report.push(ValidationSeverity::Error, theme_name, e.to_string());
+            return report;
+        }
+    };
+
+    // Every sound file in the theme's own directories is checked, along with the files the mapping uses
+    let mut sound_files = theme_paths
+        .iter()
+        .flat_map(|theme_path| fs::read_dir(theme_path).into_iter().flatten().flatten())
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.is_file()
+                && path
+                    .extension()
+                    .is_some_and(|ext| theme.sound_exts.iter().any(|sound_ext| ext == OsStr::new(sound_ext)))
+        })
+        .map(|path| path.display().to_string())
+        .collect::<Vec<_>>();
+    sound_files.sort();
+
+    let mut entries = theme.mapping.iter().collect::<Vec<_>>();
+    entries.sort_by_key(|(key, _)| *key);
+
+    // Rules with capture groups only know their names once they match a sound name
+    let rule_entries = theme
+        .mapping
+        .rules()
+        .iter()
+        .filter(|rule| !rule.entry.candidates().iter().any(|candidate| candidate.name.contains('$')))
+        .map(|rule| (&rule.key, &rule.entry));
+
+    for (key, entry) in entries.into_iter().chain(rule_entries) {
+        let mapped_files = validate_mapping_entry(&mut report, theme_name, key, entry, &theme.mapping, &lookup_paths);
+
+        for mapped_file in mapped_files {
+            if !sound_files.contains(&mapped_file) {
+                sound_files.push(mapped_file);
+            }
+        }
+    }
+
+    for sound_file in sound_files {
+        if let Err(e) = probe_sound_file(&sound_file) {
+            report.push(
+                ValidationSeverity::Warning,
+                theme_name,
+                format!("Sound file '{sound_file}' could not be decoded:\t{e}"),
+            );
+        }
+    }
+
+    report
+}
+
+/// # Documentation
+/// Check that each name of the mapping entry has a sound file, and that its durations fit within those files. Each step of
+/// a sequence is looked up in `mapping` first, in the same way as when it is played. Returns the sound files which were
+/// found for the entry
+fn validate_mapping_entry(
+    report: &mut ValidationReport,
+    theme_name: Option<&str>,
+    key: &str,
+    entry: &MappingEntry,
+    mapping: &Mapping,
+    lookup_paths: &[(String, Vec<String>)],
+) -> Vec<String> {
+    let MappingEntry::Sequence(steps) = entry else {
+        let playback_duration = match entry.duration().as_deref().map(PlaybackDuration::from_str).transpose() {
+            Ok(playback_duration) => playback_duration,
+            Err(e) => {
+                report.push(
+                    ValidationSeverity::Error,
+                    theme_name,
+                    format!("Mapping '{key}' has an invalid duration:\t{e}"),
+                );
+                None
+            }
+        };
+        let range = match entry.range() {
+            Ok(range) => range,
+            Err(e) => {
+                report.push(
+                    ValidationSeverity::Error,
+                    theme_name,
+                    format!("Mapping '{key}' has an invalid range:\t{e}"),
+                );
+                PlaybackRange::default()
+            }
+        };
+        if let Some(Err(e)) = entry.fade_out().as_deref().map(PlaybackDuration::from_str) {
+            report.push(
+                ValidationSeverity::Error,
+                theme_name,
+                format!("Mapping '{key}' has an invalid fade-out:\t{e}"),
+            );
+        }
+
+        return validate_mapped_names(
+            report,
+            theme_name,
+            &format!("Mapping '{key}'"),
+            &entry.candidates(),
+            &range,
+            playback_duration.as_ref(),
+            lookup_paths,
+        );
+    };
+
+    let mut mapped_files = Vec::new();
+    for step in steps {
+        let SequenceStep::Sound {
+            name, duration, range, ..
+        } = step
+        else {
+            continue;
+        };
+
+        // The values of each step are checked when the config is read, and the values of the entry it is mapped to are
+        // reported with that entry's own key, so neither are reported again here
+        let step_entry = mapping.find(name).filter(|step_entry| !step_entry.is_sequence());
+        let overrides = PlaybackOverrides {
+            duration: duration
+                .as_deref()
+                .and_then(|duration| PlaybackDuration::from_str(duration).ok()),
+            range: range
+                .as_deref()
+                .and_then(|range| PlaybackRange::from_str(range).ok())
+                .unwrap_or_default(),
+            ..PlaybackOverrides::default()
+        };
+
+        let range = overrides.merge_range(
+            step_entry
+                .as_ref()
+                .and_then(|step_entry| step_entry.range().ok())
+                .unwrap_or_default(),
+        );
+        let playback_duration = overrides.duration.clone().or_else(|| {
+            step_entry
+                .as_ref()
+                .and_then(MappingEntry::duration)
+                .and_then(|duration| PlaybackDuration::from_str(&duration).ok())
+        });
+        let candidates = step_entry.map_or_else(|| vec![MappingCandidate::new(name)], |step_entry| step_entry.candidates());
+
+        mapped_files.extend(validate_mapped_names(
+            report,
+            theme_name,
+            &format!("Mapping '{key}' step '{name}'"),
+            &candidates,
+            &range,
+            playback_duration.as_ref(),
+            lookup_paths,
+        ));
+    }
+
+    mapped_files
+}
+
+/// # Documentation
+/// Check that each of the names has a sound file, and that the range and duration fit within those files, where `subject`
+/// describes what the names were mapped by. Returns the sound files which were found for the names
+fn validate_mapped_names(
+    report: &mut ValidationReport,
+    theme_name: Option<&str>,
+    subject: &str,
+    candidates: &[MappingCandidate],
+    range: &PlaybackRange,
+    playback_duration: Option<&PlaybackDuration>,
+    lookup_paths: &[(String, Vec<String>)],
+) -> Vec<String> {
+    let mut mapped_files = Vec::new();
+    for candidate in candidates {
+        // Less specific names are used when a sound is played, so they count as a match here too
+        let Some(sound_file) = get_fallback_names(&candidate.name).iter().find_map(|name| {
+            lookup_paths.iter().find_map(|(lookup_path, sound_exts)| {
+                sound_exts
+                    .iter()
+                    .map(|sound_ext| format!("{lookup_path}/{name}.{sound_ext}"))
+                    .find(|sound_path_str| Path::new(sound_path_str).is_file())
+            })
+        }) else {
+            report.push(
+                ValidationSeverity::Warning,
+                theme_name,
+                format!("{subject} points at '{}', which has no sound file", candidate.name),
+            );
+            continue;
+        };
+
+        // Files which can't be decoded are reported separately
+        if probe_sound_file(&sound_file).is_ok()
+            && let Err(e) = playback_range_to_durations(range, playback_duration, &sound_file)
+        {
+            report.push(
+                ValidationSeverity::Error,
+                theme_name,
+                format!("{subject} doesn't fit within '{sound_file}':\t{e}"),
+            );
+        }
+
+        mapped_files.push(sound_file);
+    }
+
+    mapped_files
+}
@@ -0,0 +1,201 @@
+use std::{fmt, str::FromStr};
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::error::ThemerError;
+
+/// # Documentation
+/// The loudest volume which can be set, as a linear multiplier
+pub const MAX_VOLUME: f32 = 1.5;
+
+/// # Documentation
+/// A linear volume multiplier from 0.0 to `MAX_VOLUME`, where 1.0 leaves the sound unchanged
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+pub struct Volume(f32);
+
+impl Default for Volume {
+    fn default() -> Self {
+        Self(1.0)
+    }
+}
+
+impl Volume {
+    /// # Errors
+    /// Returns an error if `value` is not between 0.0 and `MAX_VOLUME`
+    pub fn new(value: f32) -> Result<Self, ThemerError> {
+        if (0.0..=MAX_VOLUME).contains(&value) {
+            Ok(Self(value))
+        } else {
+            Err(ThemerError::VolumeFromStrError {
+                from: value.to_string(),
+                e: format!(
+                    "Volume must be between 0.0 and {MAX_VOLUME:.1} (0% and {:.0}%)",
+                    MAX_VOLUME * 100.0
+                ),
+            })
+        }
+    }
+
+    /// # Documentation
+    /// Get the volume as a linear multiplier
+    #[must_use]
+    pub const fn value(self) -> f32 {
+        self.0
+    }
+
+    /// # Documentation
+    /// Multiply the volumes of each layer together, limiting the result to `MAX_VOLUME`
+    #[must_use]
+    pub fn product<I: IntoIterator<Item = Self>>(volumes: I) -> Self {
+        Self(volumes.into_iter().map(Self::value).product::<f32>().min(MAX_VOLUME))
+    }
+}
+
+impl fmt::Display for Volume {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl FromStr for Volume {
+    type Err = ThemerError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim().to_ascii_lowercase();
+
+        // It's a percentage
+        if let Some(percent) = s.strip_suffix("%") {
+            let value = percent.trim().parse::<f32>().map_err(|_| ThemerError::VolumeFromStrError {
+                from: s.clone(),
+                e: String::from("Percentage must be a numerical value followed by '%'"),
+            })?;
+
+            Self::new(value / 100.0)
+        } else if let Some(decibels) = s.strip_suffix("db") {
+            // Convert the decibels into a linear multiplier
+            let value = decibels.trim().parse::<f32>().map_err(|_| ThemerError::VolumeFromStrError {
+                from: s.clone(),
+                e: String::from("Decibel value must be a numerical value followed by 'dB'"),
+            })?;
+
+            Self::new(10_f32.powf(value / 20.0))
+        } else {
+            let value = s.parse::<f32>().map_err(|_| ThemerError::VolumeFromStrError {
+                from: s.clone(),
+                e: String::from("Volume must be a numerical value, a percentage, or a decibel value (e.g: 0.8, 80%, -3dB)"),
+            })?;
+
+            Self::new(value)
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Volume {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        // Allow the volume to be given as a number, or as a string using the same format as `--volume`
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum VolumeValue {
+            Float(f32),
+            Integer(i64),
+            String(String),
+        }
+
+        #[allow(clippy::cast_precision_loss)]
+        match VolumeValue::deserialize(deserializer)? {
+            VolumeValue::Float(value) => Self::new(value),
+            VolumeValue::Integer(value) => Self::new(value as f32),
+            VolumeValue::String(value) => Self::from_str(value.as_str()),
+        }
+        .map_err(serde::de::Error::custom)
+    }
+}
+
+impl Serialize for Volume {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        // Write the shortest decimal which reads back as the same f32 (e.g: 0.8, rather than 0.800000011920929)
+        serializer.serialize_f64(self.0.to_string().parse().unwrap_or_else(|_| f64::from(self.0)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_volume(volume_str: &str, expected: f32) -> Result<(), ThemerError> {
+        let volume = Volume::from_str(volume_str)?.value();
+        assert!(
+            (volume - expected).abs() < 0.001,
+            "'{volume_str}' gave {volume}, not {expected}"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn volumes_are_read_in_each_form() -> Result<(), ThemerError> {
+        assert_volume("0.8", 0.8)?;
+        assert_volume(" 1 ", 1.0)?;
+        assert_volume("80%", 0.8)?;
+        assert_volume("150 %", 1.5)?;
+        assert_volume("0dB", 1.0)?;
+        assert_volume("-6dB", 0.501)?;
+        assert_volume("-20 db", 0.1)?;
+        assert_volume("3.5DB", 1.496)?;
+
+        for volume_str in ["loud", "%", "80%%", "dB", "0.8x", ""] {
+            assert!(Volume::from_str(volume_str).is_err(), "'{volume_str}' was accepted");
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn volumes_outside_the_range_are_errors() {
+        for volume_str in ["-0.1", "1.6", "151%", "-5%", "4dB"] {
+            assert!(
+                matches!(Volume::from_str(volume_str), Err(ThemerError::VolumeFromStrError { .. })),
+                "'{volume_str}' was accepted"
+            );
+        }
+
+        assert!(Volume::new(MAX_VOLUME).is_ok());
+        assert!(Volume::new(f32::NAN).is_err());
+    }
+
+    #[test]
+    fn products_are_limited_to_the_max_volume() -> Result<(), ThemerError> {
+        let product = Volume::product([Volume::new(0.5)?, Volume::new(0.5)?]);
+        assert!((product.value() - 0.25).abs() < f32::EPSILON);
+
+        let product = Volume::product([Volume::new(1.5)?, Volume::new(1.5)?, Volume::new(1.2)?]);
+        assert!((product.value() - MAX_VOLUME).abs() < f32::EPSILON);
+
+        assert!((Volume::product([]).value() - 1.0).abs() < f32::EPSILON);
+
+        Ok(())
+    }
+
+    #[test]
+    fn volumes_are_deserialized_from_numbers_or_strings() -> Result<(), toml::de::Error> {
+        #[derive(Deserialize)]
+        struct VolumeTable {
+            volume: Volume,
+        }
+
+        for (table_str, expected) in [
+            ("volume = 0.8", 0.8),
+            ("volume = 1", 1.0),
+            (r#"volume = "80%""#, 0.8),
+            (r#"volume = "-6dB""#, 0.501),
+        ] {
+            let volume = toml::from_str::<VolumeTable>(table_str)?.volume.value();
+            assert!((volume - expected).abs() < 0.001, "'{table_str}' gave {volume}");
+        }
+
+        assert!(toml::from_str::<VolumeTable>("volume = 2").is_err());
+        assert!(toml::from_str::<VolumeTable>(r#"volume = "loud""#).is_err());
+
+        Ok(())
+    }
+}
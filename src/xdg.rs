@@ -0,0 +1,129 @@
+use std::{
+    fs::{self, DirBuilder},
+    io::ErrorKind,
+    os::unix::fs::{DirBuilderExt, MetadataExt},
+};
+
+use crate::error::ThemerError;
+
+/// # Documentation
+/// Directories to use when `$XDG_DATA_DIRS` is unset, as defined by the XDG Base Directory Specification
+pub const DEFAULT_DATA_DIRS: &[&str] = &["/usr/local/share", "/usr/share"];
+
+/// # Documentation
+/// Get the value of an environment variable which holds an absolute path, ignoring it if it is empty or relative
+#[must_use]
+pub fn get_env_path<S: AsRef<str>>(var: S) -> Option<String> {
+    std::env::var(var.as_ref()).ok().filter(|path| path.starts_with('/'))
+}
+
+/// # Documentation
+/// Directories to use when `$XDG_CONFIG_DIRS` is unset, as defined by the XDG Base Directory Specification
+pub const DEFAULT_CONFIG_DIRS: &[&str] = &["/etc/xdg"];
+
+/// # Documentation
+/// Get the paths from an environment variable which holds a colon-separated list, ignoring any empty or relative entries
+#[must_use]
+pub fn get_env_path_list<S: AsRef<str>>(var: S) -> Option<Vec<String>> {
+    let paths = std::env::var(var.as_ref())
+        .ok()?
+        .split(':')
+        .filter(|path| path.starts_with('/'))
+        .map(ToString::to_string)
+        .collect::<Vec<_>>();
+
+    if paths.is_empty() { None } else { Some(paths) }
+}
+
+/// # Documentation
+/// Get the `$HOME` directory
+#[must_use]
+pub fn get_home_dir() -> Option<String> {
+    get_env_path("HOME")
+}
+
+/// # Documentation
+/// Get `$XDG_DATA_HOME`, falling back to `$HOME/.local/share`
+#[must_use]
+pub fn get_data_home() -> Option<String> {
+    get_env_path("XDG_DATA_HOME").or_else(|| get_home_dir().map(|home| format!("{home}/.local/share")))
+}
+
+/// # Documentation
+/// Get `$XDG_CONFIG_HOME`, falling back to `$HOME/.config`
+#[must_use]
+pub fn get_config_home() -> Option<String> {
+    get_env_path("XDG_CONFIG_HOME").or_else(|| get_home_dir().map(|home| format!("{home}/.config")))
+}
+
+/// # Documentation
+/// Get each entry of `$XDG_CONFIG_DIRS`, falling back to `/etc/xdg`
+#[must_use]
+pub fn get_config_dirs() -> Vec<String> {
+    get_env_path_list("XDG_CONFIG_DIRS").unwrap_or_else(|| DEFAULT_CONFIG_DIRS.iter().map(ToString::to_string).collect())
+}
+
+/// # Documentation
+/// Get `$XDG_STATE_HOME`, falling back to `$HOME/.local/state`
+#[must_use]
+pub fn get_state_home() -> Option<String> {
+    get_env_path("XDG_STATE_HOME").or_else(|| get_home_dir().map(|home| format!("{home}/.local/state")))
+}
+
+/// # Documentation
+/// Get `$XDG_CACHE_HOME`, falling back to `$HOME/.cache`
+#[must_use]
+pub fn get_cache_home() -> Option<String> {
+    get_env_path("XDG_CACHE_HOME").or_else(|| get_home_dir().map(|home| format!("{home}/.cache")))
+}
+
+/// # Documentation
+/// Get each entry of `$XDG_DATA_DIRS`, falling back to `/usr/local/share` and `/usr/share`
+#[must_use]
+pub fn get_data_dirs() -> Vec<String> {
+    get_env_path_list("XDG_DATA_DIRS").unwrap_or_else(|| DEFAULT_DATA_DIRS.iter().map(ToString::to_string).collect())
+}
+
+/// # Documentation
+/// Get `$XDG_RUNTIME_DIR`, falling back to the system's temporary directory
+#[must_use]
+pub fn get_runtime_dir() -> String {
+    get_env_path("XDG_RUNTIME_DIR").unwrap_or_else(|| std::env::temp_dir().display().to_string())
+}
+
+/// # Documentation
+/// Get a directory which only the current user can use, which is `$XDG_RUNTIME_DIR`, falling back to a directory named
+/// `sound_themer-<uid>` in the system's temporary directory, which is created with mode 0700
+///
+/// # Errors
+/// Returns an error if the fallback directory could not be created
+/// Returns an error if the fallback directory isn't a directory, isn't owned by the current user, or can be used by other
+/// users (e.g: it was created by another user first)
+pub fn get_private_runtime_dir() -> Result<String, ThemerError> {
+    if let Some(runtime_dir) = get_env_path("XDG_RUNTIME_DIR") {
+        return Ok(runtime_dir);
+    }
+
+    // SAFETY: `getuid()` has no preconditions and can't fail
+    let uid = unsafe { libc::getuid() };
+    let runtime_dir = format!("{}/sound_themer-{uid}", std::env::temp_dir().display());
+    let runtime_dir_error = |e: String| ThemerError::RuntimeDirError {
+        path: runtime_dir.clone(),
+        e,
+    };
+
+    match DirBuilder::new().mode(0o700).create(&runtime_dir) {
+        Err(e) if e.kind() != ErrorKind::AlreadyExists => return Err(runtime_dir_error(e.to_string())),
+        _ => {}
+    }
+
+    // The directory is shared with other users, so it isn't used unless only the current user can use it
+    let metadata = fs::symlink_metadata(&runtime_dir).map_err(|e| runtime_dir_error(e.to_string()))?;
+    if !metadata.is_dir() || metadata.uid() != uid || metadata.mode() & 0o077 != 0 {
+        return Err(runtime_dir_error(String::from(
+            "It must be a directory which is owned by the current user, and which other users can't use",
+        )));
+    }
+
+    Ok(runtime_dir)
+}
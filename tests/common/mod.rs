@@ -0,0 +1,147 @@
+// Helpers shared by the integration tests, which run the `sound_themer` binary against a config and themes in a
+// temporary directory, so the user's own config, themes, cache, and state are never used
+#![allow(dead_code)]
+
+use std::{
+    fs,
+    io::Write,
+    os::unix::fs::PermissionsExt,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use tempfile::TempDir;
+
+pub struct TestEnv {
+    pub dir: TempDir,
+}
+
+impl TestEnv {
+    pub fn new() -> std::io::Result<Self> {
+        let env = Self {
+            dir: tempfile::tempdir()?,
+        };
+
+        for dir in ["config", "data", "cache", "state", "runtime"] {
+            fs::create_dir_all(env.path(dir))?;
+        }
+        fs::set_permissions(env.path("runtime"), fs::Permissions::from_mode(0o700))?;
+
+        Ok(env)
+    }
+
+    pub fn path(&self, relative: &str) -> PathBuf {
+        self.dir.path().join(relative)
+    }
+
+    pub fn config_path(&self) -> PathBuf {
+        self.path("config/sound_themer/config.toml")
+    }
+
+    // Write the user's config file, which is the only config layer that is used
+    pub fn write_config(&self, config: &str) -> std::io::Result<()> {
+        let config_path = self.config_path();
+        if let Some(dir) = config_path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+
+        fs::write(config_path, config)
+    }
+
+    // Write a mono 16-bit WAV file of `samples` at 8000Hz to `relative`
+    pub fn write_wav(&self, relative: &str, samples: usize) -> std::io::Result<PathBuf> {
+        let path = self.path(relative);
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+
+        let data_len = u32::try_from(samples * 2).unwrap_or(u32::MAX);
+        let mut wav = Vec::new();
+        wav.write_all(b"RIFF")?;
+        wav.write_all(&(36 + data_len).to_le_bytes())?;
+        wav.write_all(b"WAVEfmt ")?;
+        wav.write_all(&16_u32.to_le_bytes())?;
+        wav.write_all(&1_u16.to_le_bytes())?;
+        wav.write_all(&1_u16.to_le_bytes())?;
+        wav.write_all(&8000_u32.to_le_bytes())?;
+        wav.write_all(&16_000_u32.to_le_bytes())?;
+        wav.write_all(&2_u16.to_le_bytes())?;
+        wav.write_all(&16_u16.to_le_bytes())?;
+        wav.write_all(b"data")?;
+        wav.write_all(&data_len.to_le_bytes())?;
+        for i in 0..samples {
+            let sample: i16 = if i % 16 < 8 { 8000 } else { -8000 };
+            wav.write_all(&sample.to_le_bytes())?;
+        }
+
+        fs::write(&path, wav)?;
+
+        Ok(path)
+    }
+
+    // Write a player script at `relative`, which appends its arguments to `played`, one line per run
+    pub fn write_fake_player(&self, relative: &str) -> std::io::Result<PathBuf> {
+        let path = self.path(relative);
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let log_path = self.played_log_path();
+        fs::write(
+            &path,
+            format!(
+                "#!/bin/sh\nprintf '%s|' \"$@\" >> '{}'\necho >> '{}'\n",
+                log_path.display(),
+                log_path.display()
+            ),
+        )?;
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o755))?;
+
+        Ok(path)
+    }
+
+    pub fn played_log_path(&self) -> PathBuf {
+        self.path("played")
+    }
+
+    // Get the arguments of each run of the fake player
+    pub fn played(&self) -> Vec<Vec<String>> {
+        fs::read_to_string(self.played_log_path())
+            .unwrap_or_default()
+            .lines()
+            .map(|line| line.split_terminator('|').map(ToString::to_string).collect())
+            .collect()
+    }
+
+    // The binary, with every directory it reads or writes moved into the temporary directory
+    pub fn command(&self) -> Command {
+        let mut command = Command::new(env!("CARGO_BIN_EXE_sound_themer"));
+
+        for var in [
+            "SOUND_THEMER_CONFIG",
+            "SOUND_THEMER_THEME",
+            "SOUND_THEMER_VOLUME",
+            "SOUND_THEMER_FADE_OUT",
+            "SOUND_THEMER_PLAYER",
+            "SOUND_THEMER_PLAYER_COMMAND",
+            "SOUND_THEMER_SEARCH_PATHS",
+        ] {
+            command.env_remove(var);
+        }
+
+        command
+            .env("HOME", self.dir.path())
+            .env("XDG_CONFIG_HOME", self.path("config"))
+            .env("XDG_CONFIG_DIRS", self.path("config-dirs"))
+            .env("XDG_DATA_HOME", self.path("data"))
+            .env("XDG_DATA_DIRS", self.path("data-dirs"))
+            .env("XDG_CACHE_HOME", self.path("cache"))
+            .env("XDG_STATE_HOME", self.path("state"))
+            .env("XDG_RUNTIME_DIR", self.path("runtime"));
+
+        command
+    }
+}
+
+pub fn path_str(path: &Path) -> String {
+    path.display().to_string()
+}
@@ -0,0 +1,47 @@
+mod common;
+
+use std::fs;
+
+use common::{TestEnv, path_str};
+
+#[test]
+fn config_path_prints_every_layer() -> Result<(), Box<dyn std::error::Error>> {
+    let env = TestEnv::new()?;
+    env.write_config("volume = 0.5")?;
+    let system_config_path = env.path("config-dirs/sound_themer/config.toml");
+    fs::create_dir_all(env.path("config-dirs/sound_themer"))?;
+    fs::write(&system_config_path, "volume = 0.8")?;
+
+    let output = env.command().args(["config", "path"]).output()?;
+    assert!(output.status.success(), "{output:?}");
+
+    // The user's config overrides the system config, so it is printed first
+    let stdout = String::from_utf8(output.stdout)?;
+    let config_paths = stdout.lines().take(2).collect::<Vec<_>>();
+    assert_eq!(
+        config_paths,
+        vec![path_str(&env.config_path()), path_str(&system_config_path)]
+    );
+
+    Ok(())
+}
+
+#[test]
+fn config_path_prints_only_the_selected_file() -> Result<(), Box<dyn std::error::Error>> {
+    let env = TestEnv::new()?;
+    env.write_config("volume = 0.5")?;
+    let selected_config_path = env.path("selected.toml");
+    fs::write(&selected_config_path, "volume = 0.8")?;
+
+    let output = env
+        .command()
+        .args(["--config", &path_str(&selected_config_path), "config", "path"])
+        .output()?;
+    assert!(output.status.success(), "{output:?}");
+    assert_eq!(
+        String::from_utf8(output.stdout)?,
+        format!("{}\n", path_str(&selected_config_path))
+    );
+
+    Ok(())
+}
@@ -0,0 +1,154 @@
+// Runs the D-Bus service on a private session bus, started with `dbus-daemon --session`, and plays events through it.
+// The tests are skipped if `dbus-daemon` isn't installed
+#![cfg(feature = "dbus")]
+
+mod common;
+
+use std::{
+    collections::HashMap,
+    io::{BufRead, BufReader},
+    process::{Child, Command, Stdio},
+    thread,
+    time::{Duration, Instant},
+};
+
+use common::{TestEnv, path_str};
+use sound_themer::dbus::{DBUS_INTERFACE, DBUS_NAME, DBUS_PATH};
+use zbus::blocking::{Connection, connection};
+
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+// Kills the process when it is dropped, so nothing is left running if a test fails
+struct ChildGuard(Child);
+
+impl Drop for ChildGuard {
+    fn drop(&mut self) {
+        let _ = self.0.kill();
+        let _ = self.0.wait();
+    }
+}
+
+// Start a private session bus, giving its address, or `None` if `dbus-daemon` couldn't be started
+fn start_bus() -> Option<(ChildGuard, String)> {
+    let mut bus = ChildGuard(
+        Command::new("dbus-daemon")
+            .args(["--session", "--nofork", "--print-address"])
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .ok()?,
+    );
+
+    let mut address = String::new();
+    BufReader::new(bus.0.stdout.take()?).read_line(&mut address).ok()?;
+    let address = address.trim().to_string();
+
+    (!address.is_empty()).then_some((bus, address))
+}
+
+// Connect to the bus, then wait until the service owns its name
+fn connect_to_service(address: &str) -> zbus::Result<Connection> {
+    let connection = connection::Builder::address(address)?.build()?;
+
+    let start = Instant::now();
+    loop {
+        let owned = connection
+            .call_method(
+                Some("org.freedesktop.DBus"),
+                "/org/freedesktop/DBus",
+                Some("org.freedesktop.DBus"),
+                "NameHasOwner",
+                &DBUS_NAME,
+            )?
+            .body()
+            .deserialize::<bool>()?;
+
+        if owned || start.elapsed() > TIMEOUT {
+            return Ok(connection);
+        }
+        thread::sleep(Duration::from_millis(20));
+    }
+}
+
+fn play_event(connection: &Connection, event_id: &str, properties: &[(&str, &str)]) -> zbus::Result<()> {
+    let properties = properties
+        .iter()
+        .map(|(key, value)| ((*key).to_string(), (*value).to_string()))
+        .collect::<HashMap<_, _>>();
+
+    connection.call_method(
+        Some(DBUS_NAME),
+        DBUS_PATH,
+        Some(DBUS_INTERFACE),
+        "PlayEvent",
+        &(event_id, properties),
+    )?;
+
+    Ok(())
+}
+
+// Wait until the fake player has been run `count` times
+fn wait_for_played(env: &TestEnv, count: usize) -> Vec<Vec<String>> {
+    let start = Instant::now();
+    while env.played().len() < count && start.elapsed() < TIMEOUT {
+        thread::sleep(Duration::from_millis(20));
+    }
+
+    env.played()
+}
+
+#[test]
+fn play_event_plays_the_mapped_sound() -> Result<(), Box<dyn std::error::Error>> {
+    let Some((_bus, address)) = start_bus() else {
+        eprintln!("Skipping, since dbus-daemon couldn't be started");
+        return Ok(());
+    };
+
+    let env = TestEnv::new()?;
+    let bell_path = env.write_wav("sounds/test/stereo/bell.wav", 800)?;
+    let player_path = env.write_fake_player("fake-player")?;
+    env.write_config(&format!(
+        r#"
+theme_name = "test"
+search_paths = ["{}"]
+
+[player]
+backend = "command"
+command = "'{}' {{volume}} {{path}}"
+
+[[themes]]
+name = "test"
+sound_exts = ["wav"]
+directories = ["stereo"]
+mapping = {{ ding = "bell" }}
+"#,
+        path_str(&env.path("sounds")),
+        path_str(&player_path),
+    ))?;
+
+    let _service = ChildGuard(
+        env.command()
+            .arg("dbus")
+            .env("DBUS_SESSION_BUS_ADDRESS", &address)
+            .stdout(Stdio::null())
+            .spawn()?,
+    );
+    let connection = connect_to_service(&address)?;
+
+    play_event(&connection, "ding", &[("canberra.volume", "-6")])?;
+    let played = wait_for_played(&env, 1);
+    assert_eq!(played.len(), 1, "{played:?}");
+    assert_eq!(played[0][1], path_str(&bell_path));
+    let volume = played[0][0].parse::<f32>()?;
+    assert!(
+        (volume - 0.501).abs() < 0.01,
+        "canberra.volume -6dB gave a volume of {volume}"
+    );
+
+    // Unknown events are returned as errors, instead of being played
+    let result = play_event(&connection, "no-such-event", &[]);
+    assert!(result.is_err(), "{result:?}");
+    assert_eq!(env.played().len(), 1);
+
+    Ok(())
+}
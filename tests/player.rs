@@ -0,0 +1,123 @@
+// Plays sounds with the `sound_themer` binary, using fake players which record the arguments they are run with
+mod common;
+
+use std::process::Output;
+
+use common::{TestEnv, path_str};
+
+// Write a theme called `test` which has a `bell.wav`, in a search path containing a space, played by `command`
+fn write_theme(env: &TestEnv, player_config: &str) -> std::io::Result<String> {
+    let bell_path = env.write_wav("my sounds/test/stereo/bell.wav", 8000)?;
+    env.write_config(&format!(
+        r#"
+theme_name = "test"
+search_paths = ["{}"]
+
+[player]
+{player_config}
+
+[[themes]]
+name = "test"
+sound_exts = ["wav"]
+directories = ["stereo"]
+"#,
+        path_str(&env.path("my sounds")),
+    ))?;
+
+    Ok(path_str(&bell_path))
+}
+
+fn play(env: &TestEnv, args: &[&str]) -> std::io::Result<Output> {
+    let output = env.command().args(["play", "--no-daemon"]).args(args).output()?;
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+
+    Ok(output)
+}
+
+#[test]
+fn command_placeholders_are_replaced() -> Result<(), Box<dyn std::error::Error>> {
+    let env = TestEnv::new()?;
+    let player_path = env.write_fake_player("my players/fake-player")?;
+    let bell_path = write_theme(
+        &env,
+        &format!(
+            r#"command = "'{}' {{path}} {{volume}} {{volume_percent}}% '{{duration}}'""#,
+            path_str(&player_path)
+        ),
+    )?;
+
+    play(&env, &["--volume", "50%", "bell"])?;
+    assert_eq!(
+        env.played(),
+        vec![vec![bell_path, String::from("0.5"), String::from("50%"), String::new()]]
+    );
+
+    Ok(())
+}
+
+#[test]
+fn command_is_given_a_trimmed_copy_for_a_duration() -> Result<(), Box<dyn std::error::Error>> {
+    let env = TestEnv::new()?;
+    let player_path = env.write_fake_player("fake-player")?;
+    let bell_path = write_theme(
+        &env,
+        &format!(r#"command = "'{}' {{path}} {{volume}} {{duration}}""#, path_str(&player_path)),
+    )?;
+
+    play(&env, &["bell@250ms"])?;
+    let played = env.played();
+    assert_eq!(played.len(), 1, "{played:?}");
+    assert_ne!(played[0][0], bell_path);
+    assert!(played[0][0].starts_with(&path_str(&env.path("runtime"))), "{played:?}");
+    assert_eq!(played[0][1..], [String::from("1"), String::from("0.25")]);
+
+    Ok(())
+}
+
+#[test]
+fn command_without_volume_is_given_a_copy_at_the_volume() -> Result<(), Box<dyn std::error::Error>> {
+    let env = TestEnv::new()?;
+    let player_path = env.write_fake_player("fake-player")?;
+    let bell_path = write_theme(&env, &format!(r#"command = "'{}' {{path}}""#, path_str(&player_path)))?;
+
+    // The sound is played as it is at full volume
+    play(&env, &["bell"])?;
+    play(&env, &["--volume", "0.5", "bell"])?;
+
+    let played = env.played();
+    assert_eq!(played.len(), 2, "{played:?}");
+    assert_eq!(played[0], vec![bell_path.clone()]);
+    assert_ne!(played[1][0], bell_path);
+    assert!(played[1][0].starts_with(&path_str(&env.path("runtime"))), "{played:?}");
+
+    Ok(())
+}
+
+#[test]
+#[cfg(not(feature = "native"))]
+fn auto_backend_detects_the_installed_player() -> Result<(), Box<dyn std::error::Error>> {
+    let env = TestEnv::new()?;
+    env.write_fake_player("bin/aplay")?;
+    env.write_fake_player("bin/ffplay")?;
+    let bell_path = write_theme(&env, r#"backend = "auto""#)?;
+
+    // aplay is found before ffplay, and pw-play and paplay aren't in $PATH
+    let output = env
+        .command()
+        .env("PATH", env.path("bin"))
+        .args(["play", "--no-daemon", "bell"])
+        .output()?;
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert_eq!(env.played(), vec![vec![String::from("-q"), bell_path]]);
+
+    // No player is found if none of them are in $PATH
+    let output = env
+        .command()
+        .env("PATH", env.path("empty"))
+        .args(["play", "--no-daemon", "bell"])
+        .output()?;
+    assert!(!output.status.success());
+    assert_eq!(env.played().len(), 1);
+
+    Ok(())
+}
@@ -0,0 +1,159 @@
+// Validates a config which points at themes in a temporary directory, checking the problems found and their severities
+mod common;
+
+use std::{fs, sync::Mutex};
+
+use common::{TestEnv, path_str};
+use sound_themer::{
+    config::{ValidationIssue, ValidationSeverity, select_config_path, validate_config},
+    theme::{get_theme_from_name, validate_theme},
+};
+
+// The selected config file is global, so only one test can use it at once
+static CONFIG_LOCK: Mutex<()> = Mutex::new(());
+
+// Select the config, which should remove the built-in mapping with `mapping = {}`, keeping only the issues in `theme_names`, since the built-in themes depend on the system
+fn validate(env: &TestEnv, config: &str, theme_names: &[&str]) -> Result<Vec<ValidationIssue>, Box<dyn std::error::Error>> {
+    env.write_config(config)?;
+    select_config_path(Some(path_str(&env.config_path())))?;
+
+    Ok(validate_config()?
+        .issues
+        .into_iter()
+        .filter(|issue| issue.theme.as_deref().is_none_or(|theme| theme_names.contains(&theme)))
+        .collect())
+}
+
+fn assert_issues(issues: &[ValidationIssue], expected: &[(ValidationSeverity, &str, &str)]) {
+    assert_eq!(issues.len(), expected.len(), "{issues:#?}");
+
+    for (issue, (severity, theme, message)) in issues.iter().zip(expected) {
+        assert_eq!(issue.severity, *severity, "{issue}");
+        assert_eq!(issue.theme.as_deref(), Some(*theme), "{issue}");
+        assert!(issue.message.starts_with(message), "'{issue}' doesn't start with '{message}'");
+    }
+}
+
+#[test]
+fn theme_problems_are_reported() -> Result<(), Box<dyn std::error::Error>> {
+    let _lock = CONFIG_LOCK.lock().map_err(|e| e.to_string())?;
+
+    let env = TestEnv::new()?;
+    let sounds_path = path_str(&env.path("sounds"));
+    // 8000 samples at 8000Hz is 1s long
+    let bell_path = path_str(&env.write_wav("sounds/broken-theme/stereo/bell.wav", 8000)?);
+    fs::write(env.path("sounds/broken-theme/stereo/garbled.wav"), "not a sound file")?;
+    env.write_wav("sounds/duplicate/stereo/bell.wav", 8000)?;
+
+    let issues = validate(
+        &env,
+        &format!(
+            r#"
+theme_name = "broken-theme"
+search_paths = ["{sounds_path}"]
+mapping = {{}}
+
+[[themes]]
+name = "broken-theme"
+sound_exts = ["wav"]
+directories = ["stereo", "missing"]
+mapping = {{
+  bell = "bell",
+  no-file = "no-such-sound",
+  invalid-duration = {{ name = "bell", duration = "loud" }},
+  too-long = {{ name = "bell", duration = "5s" }},
+  garbled = "garbled",
+}}
+
+[[themes]]
+name = "duplicate"
+sound_exts = ["wav"]
+directories = ["stereo"]
+
+[[themes]]
+name = "duplicate"
+sound_exts = ["wav"]
+directories = ["stereo"]
+"#
+        ),
+        &["broken-theme", "duplicate"],
+    )?;
+
+    // The mapping is checked in order of its keys, then every sound file which couldn't be decoded is reported
+    let broken_theme_issues = [
+        (
+            ValidationSeverity::Error,
+            "broken-theme",
+            "Directory 'missing' could not be found in any of the theme search paths",
+        ),
+        (
+            ValidationSeverity::Error,
+            "broken-theme",
+            "Mapping 'invalid-duration' has an invalid duration",
+        ),
+        (
+            ValidationSeverity::Warning,
+            "broken-theme",
+            "Mapping 'no-file' points at 'no-such-sound', which has no sound file",
+        ),
+        (
+            ValidationSeverity::Error,
+            "broken-theme",
+            &format!("Mapping 'too-long' doesn't fit within '{bell_path}'"),
+        ),
+        (ValidationSeverity::Warning, "broken-theme", "Sound file '"),
+    ];
+    let mut expected = vec![(ValidationSeverity::Error, "duplicate", "Theme is defined more than once")];
+    expected.extend(broken_theme_issues.iter().cloned());
+    assert_issues(&issues, &expected);
+    assert!(
+        issues[5].message.contains("garbled.wav' could not be decoded"),
+        "{}",
+        issues[5]
+    );
+
+    // The same problems are found when the theme is validated on its own
+    let theme_issues = validate_theme(&get_theme_from_name("broken-theme")?).issues;
+    assert_issues(&theme_issues, &broken_theme_issues);
+
+    select_config_path(None)?;
+
+    Ok(())
+}
+
+#[test]
+fn valid_theme_has_no_issues() -> Result<(), Box<dyn std::error::Error>> {
+    let _lock = CONFIG_LOCK.lock().map_err(|e| e.to_string())?;
+
+    let env = TestEnv::new()?;
+    env.write_wav("sounds/valid-theme/stereo/bell.wav", 8000)?;
+    env.write_wav("sounds/valid-theme/stereo/dialog.wav", 8000)?;
+
+    let issues = validate(
+        &env,
+        &format!(
+            r#"
+theme_name = "valid-theme"
+search_paths = ["{}"]
+mapping = {{}}
+
+[[themes]]
+name = "valid-theme"
+sound_exts = ["wav"]
+directories = ["stereo"]
+mapping = {{
+  ding = {{ name = "bell", duration = "500ms" }},
+  warning = "dialog-warning", # Falls back to dialog.wav
+  sequence = {{ sequence = [{{ name = "ding" }}, {{ gap = "200ms" }}, {{ name = "bell", duration = "50%" }}] }},
+}}
+"#,
+            path_str(&env.path("sounds"))
+        ),
+        &["valid-theme"],
+    )?;
+    assert_issues(&issues, &[]);
+
+    select_config_path(None)?;
+
+    Ok(())
+}